@@ -0,0 +1,114 @@
+use criterion::{BatchSize, BenchmarkId, Criterion, criterion_group, criterion_main};
+use std::hint::black_box;
+use std::io::{Cursor, Write};
+
+use ree_pak_core::write::{FileOptions, PakWriter};
+
+use ree_path_searcher::{PathSearcher, PathSearcherConfig};
+
+/// Number of distinct raw paths referenced by the synthetic data.
+const PATH_COUNT: usize = 20_000;
+/// Every n-th raw path has no matching entry and stays unresolved.
+const UNRESOLVED_EVERY: usize = 4;
+
+fn raw_path(i: usize) -> String {
+    let ext = ["tex", "mesh", "mdf2", "user", "msg"][i % 5];
+    format!("Synthetic/Dir{:03}/Asset_{i:06}.{ext}", i % 256)
+}
+
+fn full_path(config: &PathSearcherConfig, i: usize) -> String {
+    let raw = raw_path(i);
+    let ext = raw.rsplit('.').next().unwrap();
+    let version = config.suffix_versions(ext).unwrap().last().unwrap();
+    match ext {
+        "tex" | "mesh" => format!("natives/STM/{raw}.{version}.X64"),
+        "msg" => format!("natives/STM/{raw}.{version}.Ja"),
+        _ => format!("natives/STM/{raw}.{version}"),
+    }
+}
+
+/// Build an in-memory PAK with the resolvable targets plus "source" files whose contents
+/// reference every raw path as UTF-16 strings, like real resource files do.
+fn build_synthetic_pak(config: &PathSearcherConfig) -> Vec<u8> {
+    const SOURCE_FILES: usize = 64;
+
+    let targets: Vec<String> = (0..PATH_COUNT)
+        .filter(|i| i % UNRESOLVED_EVERY != 0)
+        .map(|i| full_path(config, i))
+        .collect();
+
+    let mut pak_bytes = Vec::new();
+    let mut writer = PakWriter::new(
+        Cursor::new(&mut pak_bytes),
+        (targets.len() + SOURCE_FILES) as u64,
+    );
+    for target in &targets {
+        writer
+            .start_file(target.as_str(), FileOptions::default())
+            .unwrap();
+        writer.write_all(b"synthetic target").unwrap();
+    }
+    for file in 0..SOURCE_FILES {
+        let mut data = b"SRC\0\0\0\0\0".to_vec();
+        for i in (file..PATH_COUNT).step_by(SOURCE_FILES) {
+            for unit in raw_path(i).encode_utf16() {
+                data.extend(unit.to_le_bytes());
+            }
+            data.extend([0, 0, 0, 0]);
+        }
+        writer
+            .start_file(
+                format!("natives/STM/Synthetic/Source_{file:03}.user.3").as_str(),
+                FileOptions::default(),
+            )
+            .unwrap();
+        writer.write_all(&data).unwrap();
+    }
+    writer.finish().unwrap();
+
+    pak_bytes
+}
+
+fn bench_suffix_resolution(c: &mut Criterion) {
+    let config = PathSearcherConfig::default();
+    let pak_bytes = build_synthetic_pak(&config);
+    let build_searcher = || {
+        PathSearcher::builder()
+            .with_config(config.clone())
+            .with_pak_file(Cursor::new(pak_bytes.as_slice()))
+            .unwrap()
+            .build()
+            .unwrap()
+    };
+
+    let mut group = c.benchmark_group("suffix_resolution");
+    group.sample_size(10);
+
+    let searcher = build_searcher();
+    let lines: Vec<String> = (0..PATH_COUNT).map(raw_path).collect();
+    group.bench_with_input(
+        BenchmarkId::new("resolve_reference_line", PATH_COUNT),
+        &lines,
+        |b, lines| {
+            b.iter(|| {
+                for line in lines {
+                    black_box(searcher.resolve_reference_line(line).unwrap());
+                }
+            });
+        },
+    );
+
+    // A fresh searcher per iteration, so the path cache does not hide resolution cost.
+    group.bench_function(BenchmarkId::new("search_pak_files", PATH_COUNT), |b| {
+        b.iter_batched(
+            &build_searcher,
+            |searcher| black_box(searcher.search_pak_files().unwrap()),
+            BatchSize::LargeInput,
+        );
+    });
+
+    group.finish();
+}
+
+criterion_group!(benches, bench_suffix_resolution);
+criterion_main!(benches);
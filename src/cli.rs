@@ -0,0 +1,320 @@
+mod config;
+mod diff;
+mod dump_diff;
+mod hash;
+mod lookup;
+mod overrides;
+mod resolve;
+mod scan;
+mod stats;
+mod unnamed;
+mod verify;
+
+use std::{
+    fs::File,
+    io::{self, BufRead},
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use clap::{Args, Parser, Subcommand, ValueEnum};
+use color_eyre::eyre::{self, Context};
+use indicatif::{ProgressBar, ProgressStyle};
+use ree_pak_core::{CloneableFile, PakReader};
+use ree_path_searcher::export::ExportFormat;
+use ree_path_searcher::game_dir;
+use ree_path_searcher::pak::MmapPakReader;
+use ree_path_searcher::{PathSearcher, PathSearcherBuilder, PathSearcherConfig};
+
+/// Without a subcommand, the flags of `scan` are accepted directly for compatibility with
+/// existing scripts.
+#[derive(Debug, Parser)]
+#[command(args_conflicts_with_subcommands = true)]
+pub struct Cli {
+    #[command(subcommand)]
+    command: Option<Command>,
+    #[command(flatten)]
+    scan: scan::ScanArgs,
+}
+
+#[derive(Debug, Subcommand)]
+enum Command {
+    /// Scan PAK files and memory dumps for paths (default).
+    Scan(scan::ScanArgs),
+    /// Resolve reference path lists against PAK files.
+    Resolve(resolve::ResolveArgs),
+    /// Check path lists against PAK files and write a cleaned list.
+    Verify(verify::VerifyArgs),
+    /// Compare the paths of two installs, e.g. before and after a patch.
+    Diff(diff::DiffArgs),
+    /// Report the paths found in only one of several dumps.
+    DumpDiff(dump_diff::DumpDiffArgs),
+    /// Print entry statistics of PAK files and list coverage.
+    Stats(stats::StatsArgs),
+    /// Print the effective resolver config as TOML.
+    Config(config::ConfigArgs),
+    /// Print the path hash and its lower/upper halves of each path.
+    Hash(hash::HashArgs),
+    /// Find the paths and PAKs matching path hashes.
+    Lookup(lookup::LookupArgs),
+    /// Report the files mod PAKs override or add, and where mods conflict.
+    Overrides(overrides::OverridesArgs),
+    /// Classify the PAK entries no path names by format, and try guessed paths on them.
+    Unnamed(unnamed::UnnamedArgs),
+}
+
+impl Cli {
+    pub fn run(self) -> eyre::Result<()> {
+        match self.command {
+            None => scan::run(self.scan),
+            Some(Command::Scan(args)) => scan::run(args),
+            Some(Command::Resolve(args)) => resolve::run(args),
+            Some(Command::Verify(args)) => verify::run(args),
+            Some(Command::Diff(args)) => diff::run(args),
+            Some(Command::DumpDiff(args)) => dump_diff::run(args),
+            Some(Command::Stats(args)) => stats::run(args),
+            Some(Command::Config(args)) => config::run(args),
+            Some(Command::Hash(args)) => hash::run(args),
+            Some(Command::Lookup(args)) => lookup::run(args),
+            Some(Command::Overrides(args)) => overrides::run(args),
+            Some(Command::Unnamed(args)) => unnamed::run(args),
+        }
+    }
+}
+
+/// Options shared by every command that resolves paths.
+#[derive(Debug, Args)]
+struct CommonArgs {
+    /// Number of threads to use.
+    #[arg(long)]
+    threads: Option<usize>,
+    /// TOML config for language/prefix/suffix resolving.
+    #[arg(long)]
+    config: Option<String>,
+    /// Collect every matching version instead of only the newest one. Reference lines that carry
+    /// a version always get every version present.
+    #[arg(long)]
+    all_versions: bool,
+}
+
+impl CommonArgs {
+    fn init_thread_pool(&self) -> eyre::Result<()> {
+        let threads = if let Some(threads) = self.threads {
+            threads.min(num_cpus::get())
+        } else {
+            num_cpus::get().min(8)
+        };
+        rayon::ThreadPoolBuilder::new()
+            .num_threads(threads)
+            .build_global()?;
+        Ok(())
+    }
+
+    fn load_config(&self) -> eyre::Result<PathSearcherConfig> {
+        let (config, source) = self.read_config()?;
+        println!("{source}");
+        Ok(config)
+    }
+
+    /// The config to use, and where it was taken from.
+    fn read_config(&self) -> eyre::Result<(PathSearcherConfig, String)> {
+        let (config, source) = if let Some(path) = &self.config {
+            (
+                PathSearcherConfig::from_toml_file(path)?,
+                format!("Loading config from {path}"),
+            )
+        } else {
+            let default_path = Path::new("config.toml");
+            if default_path.exists() {
+                (
+                    PathSearcherConfig::from_toml_file(default_path)?,
+                    format!("Loading config from {}", default_path.display()),
+                )
+            } else {
+                (
+                    PathSearcherConfig::default(),
+                    "Using built-in config".to_string(),
+                )
+            }
+        };
+
+        let config = if self.all_versions {
+            config.with_match_all_versions(true)
+        } else {
+            config
+        };
+        Ok((config, source))
+    }
+}
+
+#[derive(Debug, Args)]
+struct PakArgs {
+    /// Game install directory. Its PAK files are loaded in the engine's load order.
+    #[arg(long)]
+    game_dir: Option<PathBuf>,
+    /// Paths to pak files.
+    #[arg(short, long)]
+    pak: Vec<String>,
+    /// A list of paths to pak files. Each line is a path to a pak file.
+    #[arg(long)]
+    pak_list: Option<String>,
+    /// Skip PAK files that cannot be opened or parsed instead of aborting.
+    #[arg(long)]
+    skip_bad_paks: bool,
+}
+
+impl PakArgs {
+    fn is_empty(&self) -> bool {
+        self.game_dir.is_none() && self.pak.is_empty() && self.pak_list.is_none()
+    }
+
+    /// PAKs of `--game-dir`, then `--pak` paths, then the entries of `--pak-list`, in load order.
+    fn paths(&self) -> eyre::Result<Vec<String>> {
+        let mut paths = vec![];
+        if let Some(game_dir) = &self.game_dir {
+            let discovered = game_dir::discover_pak_files(game_dir)?;
+            eprintln!(
+                "Found {} PAK files in {}",
+                discovered.len(),
+                game_dir.display()
+            );
+            paths.extend(
+                discovered
+                    .into_iter()
+                    .map(|path| path.to_string_lossy().into_owned()),
+            );
+        }
+        paths.extend(self.pak.iter().cloned());
+        if let Some(pak_list) = &self.pak_list {
+            paths.extend(load_pak_list(pak_list)?);
+        }
+        Ok(paths)
+    }
+
+    fn searcher_builder(
+        &self,
+        config: PathSearcherConfig,
+    ) -> eyre::Result<PathSearcherBuilder<CloneableFile>> {
+        Ok(self.empty_builder(config).with_pak_paths(&self.paths()?))
+    }
+
+    /// [`Self::searcher_builder`] with the PAKs memory-mapped instead of read through files.
+    fn mapped_searcher_builder(
+        &self,
+        config: PathSearcherConfig,
+    ) -> eyre::Result<PathSearcherBuilder<MmapPakReader>> {
+        Ok(self
+            .empty_builder(config)
+            .with_mapped_pak_paths(&self.paths()?))
+    }
+
+    fn empty_builder<R: PakReader>(&self, config: PathSearcherConfig) -> PathSearcherBuilder<R> {
+        PathSearcher::builder()
+            .with_config(config)
+            .with_skip_bad_paks(self.skip_bad_paks)
+    }
+
+    fn build_searcher(
+        &self,
+        config: PathSearcherConfig,
+    ) -> eyre::Result<PathSearcher<CloneableFile>> {
+        let searcher = self.searcher_builder(config)?.build()?;
+        report_pak_loading(&searcher);
+        Ok(searcher)
+    }
+}
+
+/// Print how many PAKs were loaded, with a warning for each rejected one.
+fn report_pak_loading<R: PakReader>(searcher: &PathSearcher<R>) {
+    let loaded = searcher
+        .pak_collection()
+        .map_or(0, |collection| collection.pak_files().len());
+    let rejected = searcher.rejected_paks();
+    if loaded == 0 && rejected.is_empty() {
+        return;
+    }
+    eprintln!("Loaded {loaded} PAK files, rejected {}.", rejected.len());
+    for pak in rejected {
+        eprintln!("Warning: skipped {}: {}", pak.name, pak.error);
+    }
+}
+
+#[derive(Debug, Args)]
+struct OutputArgs {
+    /// Directory the result files are written to.
+    #[arg(long, default_value = ".")]
+    output_dir: PathBuf,
+    /// Format of the result files.
+    #[arg(long, value_enum, default_value_t = OutputFormat::Text)]
+    format: OutputFormat,
+}
+
+/// Command line names of the [`ExportFormat`]s.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum OutputFormat {
+    /// `output.list`, `output_raw.list` and `unknown.list`, one path per line.
+    Text,
+    /// `output.json`, a single array of path records.
+    Json,
+    /// `output.ndjson`, one path record per line.
+    Ndjson,
+    /// `output.csv`, one row per resolved full path.
+    Csv,
+}
+
+impl From<OutputFormat> for ExportFormat {
+    fn from(format: OutputFormat) -> Self {
+        match format {
+            OutputFormat::Text => ExportFormat::Text,
+            OutputFormat::Json => ExportFormat::Json,
+            OutputFormat::Ndjson => ExportFormat::Ndjson,
+            OutputFormat::Csv => ExportFormat::Csv,
+        }
+    }
+}
+
+fn progress_bar(len: u64) -> ProgressBar {
+    let progress_bar = ProgressBar::new(len);
+    progress_bar.enable_steady_tick(Duration::from_millis(100));
+    progress_bar.set_style(
+        ProgressStyle::default_bar()
+            .template("[{elapsed_precise}] {bar:40.cyan/blue} {pos:>7}/{len:7} {per_sec} {msg}")
+            .unwrap()
+            .progress_chars("##-"),
+    );
+    progress_bar
+}
+
+fn load_pak_list(pak_list_file: &str) -> eyre::Result<Vec<String>> {
+    let mut pak_file_list = vec![];
+    let paks = File::open(pak_list_file)
+        .wrap_err_with(|| format!("Failed to open pak list: {pak_list_file}"))?;
+    for line in io::BufReader::new(paks).lines() {
+        let line = line?;
+        if !line.is_empty() && !line.starts_with('#') {
+            pak_file_list.push(line);
+        }
+    }
+    Ok(pak_file_list)
+}
+
+/// `mib` MiB in bytes, or an error naming `flag` when that does not fit in a `u64`.
+fn mib_to_bytes(flag: &str, mib: u64) -> eyre::Result<u64> {
+    mib.checked_mul(1 << 20)
+        .ok_or_else(|| eyre::eyre!("{flag} {mib} MiB is too large"))
+}
+
+/// Load a path list, skipping blank lines and `#` comments.
+fn load_path_list(list_file: &str) -> eyre::Result<Vec<String>> {
+    let mut paths = vec![];
+    let file =
+        File::open(list_file).wrap_err_with(|| format!("Failed to open list: {list_file}"))?;
+    for line in io::BufReader::new(file).lines() {
+        let line = line?;
+        let line = line.trim();
+        if !line.is_empty() && !line.starts_with('#') {
+            paths.push(line.to_string());
+        }
+    }
+    Ok(paths)
+}
@@ -0,0 +1,39 @@
+use std::path::PathBuf;
+
+use clap::Args;
+use color_eyre::eyre::{self, Context};
+use ree_path_searcher::PathSearcherConfig;
+
+use super::CommonArgs;
+
+#[derive(Debug, Args)]
+pub struct ConfigArgs {
+    /// Print the built-in config instead of loading one.
+    #[arg(long, conflicts_with = "config")]
+    default: bool,
+    /// Write the config to this file instead of stdout.
+    #[arg(short, long)]
+    output: Option<PathBuf>,
+    #[command(flatten)]
+    common: CommonArgs,
+}
+
+pub fn run(args: ConfigArgs) -> eyre::Result<()> {
+    let config = if args.default {
+        PathSearcherConfig::default()
+    } else {
+        // The config itself may go to stdout, so where it came from goes to stderr.
+        let (config, source) = args.common.read_config()?;
+        eprintln!("{source}");
+        config
+    };
+    let toml = config.to_toml_string()?;
+
+    match &args.output {
+        Some(path) => std::fs::write(path, toml)
+            .wrap_err_with(|| format!("Failed to write config: {}", path.display()))?,
+        None => print!("{toml}"),
+    }
+
+    Ok(())
+}
@@ -0,0 +1,132 @@
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+
+use clap::Args;
+use color_eyre::eyre;
+use rayon::prelude::*;
+use ree_pak_core::PakReader;
+use ree_path_searcher::diff::{DiffCounts, PathDiff};
+use ree_path_searcher::{PathSearcher, export};
+
+use super::{CommonArgs, PakArgs, load_path_list};
+
+#[derive(Debug, Args)]
+pub struct DiffArgs {
+    /// Game directory of the old install.
+    #[arg(long)]
+    old_game_dir: Option<PathBuf>,
+    /// PAK files of the old install.
+    #[arg(long)]
+    old_pak: Vec<String>,
+    /// A list of PAK files of the old install.
+    #[arg(long)]
+    old_pak_list: Option<String>,
+    /// Game directory of the new install.
+    #[arg(long)]
+    new_game_dir: Option<PathBuf>,
+    /// PAK files of the new install.
+    #[arg(long)]
+    new_pak: Vec<String>,
+    /// A list of PAK files of the new install.
+    #[arg(long)]
+    new_pak_list: Option<String>,
+    /// Skip PAK files that cannot be opened or parsed instead of aborting.
+    #[arg(long)]
+    skip_bad_paks: bool,
+    /// Path lists resolved against both installs.
+    #[arg(long, required = true)]
+    list: Vec<String>,
+    /// Number of leading directories changes are grouped by.
+    #[arg(long, default_value_t = 2)]
+    dir_depth: usize,
+    #[command(flatten)]
+    common: CommonArgs,
+    /// Directory `added.list`, `removed.list` and `bumped.list` are written to.
+    #[arg(long, default_value = ".")]
+    output_dir: PathBuf,
+}
+
+pub fn run(args: DiffArgs) -> eyre::Result<()> {
+    let old_paks = PakArgs {
+        game_dir: args.old_game_dir,
+        pak: args.old_pak,
+        pak_list: args.old_pak_list,
+        skip_bad_paks: args.skip_bad_paks,
+    };
+    let new_paks = PakArgs {
+        game_dir: args.new_game_dir,
+        pak: args.new_pak,
+        pak_list: args.new_pak_list,
+        skip_bad_paks: args.skip_bad_paks,
+    };
+    if old_paks.is_empty() || new_paks.is_empty() {
+        eyre::bail!(
+            "diff requires both installs. Use --old-game-dir/--old-pak/--old-pak-list and --new-game-dir/--new-pak/--new-pak-list."
+        );
+    }
+
+    let searcher_config = args.common.load_config()?;
+    args.common.init_thread_pool()?;
+
+    let mut lines: Vec<String> = vec![];
+    for list in &args.list {
+        lines.extend(load_path_list(list)?);
+    }
+
+    eprintln!("Resolving {} paths against the old install..", lines.len());
+    let old = resolve_all(&old_paks.build_searcher(searcher_config.clone())?, &lines);
+    eprintln!("Resolving {} paths against the new install..", lines.len());
+    let new = resolve_all(&new_paks.build_searcher(searcher_config.clone())?, &lines);
+
+    let diff = PathDiff::new(&old, &new, &searcher_config);
+    println!(
+        "Added: {}, removed: {}, version bumped: {}, unchanged: {}",
+        diff.added.len(),
+        diff.removed.len(),
+        diff.bumped.len(),
+        diff.unchanged
+    );
+    print_groups("By extension", &diff.by_extension(&searcher_config));
+    print_groups(
+        "By directory",
+        &diff.by_directory(&searcher_config, args.dir_depth),
+    );
+
+    export::write_list(&args.output_dir, "added.list", &diff.added)?;
+    export::write_list(&args.output_dir, "removed.list", &diff.removed)?;
+    export::write_list(
+        &args.output_dir,
+        "bumped.list",
+        diff.bumped.iter().map(|(old, new)| format!("{old}\t{new}")),
+    )?;
+
+    Ok(())
+}
+
+/// Full paths every line resolves to in the searcher's PAKs.
+pub(super) fn resolve_all<R: PakReader>(
+    searcher: &PathSearcher<R>,
+    lines: &[String],
+) -> Vec<String> {
+    lines
+        .par_iter()
+        .flat_map_iter(|line| {
+            searcher
+                .resolve_reference_line(line)
+                .unwrap_or_default()
+                .into_iter()
+                .map(|info| info.full_path)
+        })
+        .collect()
+}
+
+fn print_groups(title: &str, groups: &BTreeMap<String, DiffCounts>) {
+    println!("{title}:");
+    for (group, counts) in groups {
+        let group = if group.is_empty() { "<root>" } else { group };
+        println!(
+            "  {group}: +{} -{} ~{}",
+            counts.added, counts.removed, counts.bumped
+        );
+    }
+}
@@ -0,0 +1,148 @@
+use std::path::{Path, PathBuf};
+
+use clap::Args;
+use color_eyre::eyre;
+use ree_path_searcher::{SearchResult, export};
+use rustc_hash::{FxHashMap, FxHashSet};
+
+use super::{CommonArgs, PakArgs, progress_bar};
+
+#[derive(Debug, Args)]
+pub struct DumpDiffArgs {
+    /// Dumps to compare, e.g. taken before and after entering an area.
+    #[arg(short, long, required = true, num_args = 1.., value_name = "DMP")]
+    dmp: Vec<String>,
+    #[command(flatten)]
+    paks: PakArgs,
+    #[command(flatten)]
+    common: CommonArgs,
+    /// Directory `<dump>.unique.list` and `<dump>.unique_raw.list` are written to. Dumps sharing
+    /// a file stem are told apart by their position, e.g. `game-2.unique.list`.
+    #[arg(long, default_value = ".")]
+    output_dir: PathBuf,
+}
+
+pub fn run(args: DumpDiffArgs) -> eyre::Result<()> {
+    if args.dmp.len() < 2 {
+        eyre::bail!("dump-diff requires at least two dumps.");
+    }
+
+    let names = output_names(&args.dmp);
+
+    let searcher_config = args.common.load_config()?;
+    args.common.init_thread_pool()?;
+
+    let searcher = args.paks.build_searcher(searcher_config)?;
+
+    let mut results: Vec<SearchResult> = Vec::with_capacity(args.dmp.len());
+    for dmp in &args.dmp {
+        eprintln!("Scanning {dmp}..");
+        let progress_bar = progress_bar(100);
+        let result =
+            searcher.search_memory_dump_with_progress(dmp, |current: u64, total: u64| {
+                progress_bar.set_length(total);
+                progress_bar.set_position(current);
+            })?;
+        progress_bar.finish_with_message("Scan dump finished.");
+        results.push(result);
+    }
+
+    // Raw path -> number of dumps it was seen in.
+    let raw_sets: Vec<FxHashSet<&str>> = results.iter().map(raw_paths).collect();
+    let mut seen_in: FxHashMap<&str, usize> = FxHashMap::default();
+    for set in &raw_sets {
+        for &raw in set {
+            *seen_in.entry(raw).or_default() += 1;
+        }
+    }
+
+    for (index, (result, raw_set)) in results.iter().zip(&raw_sets).enumerate() {
+        let (dmp, name) = (&args.dmp[index], &names[index]);
+        let mut unique_raw: Vec<&str> = raw_set
+            .iter()
+            .copied()
+            .filter(|raw| seen_in[raw] == 1)
+            .collect();
+        unique_raw.sort_unstable();
+
+        let mut unique_full: Vec<&str> = result
+            .found_paths
+            .iter()
+            .filter(|(raw, _)| seen_in[raw.as_str()] == 1)
+            .flat_map(|(_, infos)| infos.iter().map(|info| info.full_path.as_str()))
+            .collect();
+        unique_full.sort_unstable();
+        unique_full.dedup();
+
+        println!(
+            "{dmp}: {} paths, {} unique ({} resolved)",
+            raw_set.len(),
+            unique_raw.len(),
+            unique_full.len()
+        );
+
+        export::write_list(
+            &args.output_dir,
+            &format!("{name}.unique.list"),
+            &unique_full,
+        )?;
+        export::write_list(
+            &args.output_dir,
+            &format!("{name}.unique_raw.list"),
+            &unique_raw,
+        )?;
+    }
+
+    Ok(())
+}
+
+/// File stem of each dump, suffixed with its 1-based position when another dump has the same stem.
+/// A suffixed name that is taken already, e.g. by a dump named like that, counts further up.
+/// Names are compared ignoring case, as file systems may.
+fn output_names(dmps: &[String]) -> Vec<String> {
+    let stems: Vec<String> = dmps
+        .iter()
+        .map(|dmp| {
+            Path::new(dmp)
+                .file_stem()
+                .map_or_else(|| dmp.clone(), |s| s.to_string_lossy().into_owned())
+        })
+        .collect();
+    let mut counts: FxHashMap<String, usize> = FxHashMap::default();
+    for stem in &stems {
+        *counts.entry(stem.to_lowercase()).or_default() += 1;
+    }
+    // Stems only one dump has keep their name, so they are taken before any suffixed one.
+    let mut used: FxHashSet<String> = counts
+        .iter()
+        .filter(|&(_, &count)| count == 1)
+        .map(|(stem, _)| stem.clone())
+        .collect();
+    stems
+        .iter()
+        .enumerate()
+        .map(|(index, stem)| {
+            if counts[&stem.to_lowercase()] == 1 {
+                return stem.clone();
+            }
+            let mut number = index + 1;
+            loop {
+                let name = format!("{stem}-{number}");
+                if used.insert(name.to_lowercase()) {
+                    return name;
+                }
+                number += 1;
+            }
+        })
+        .collect()
+}
+
+/// Every raw path found in a dump, resolved or not.
+fn raw_paths(result: &SearchResult) -> FxHashSet<&str> {
+    result
+        .found_paths
+        .iter()
+        .map(|(raw, _)| raw.as_str())
+        .chain(result.unknown_paths.iter().map(String::as_str))
+        .collect()
+}
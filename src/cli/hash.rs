@@ -0,0 +1,45 @@
+use std::io::{self, BufRead};
+
+use clap::Args;
+use color_eyre::eyre;
+use ree_path_searcher::hash::PathHasher;
+
+#[derive(Debug, Args)]
+pub struct HashArgs {
+    /// Paths to hash. Read from stdin, one per line, when omitted.
+    paths: Vec<String>,
+}
+
+pub fn run(args: HashArgs) -> eyre::Result<()> {
+    let paths = if args.paths.is_empty() {
+        read_stdin_lines()?
+    } else {
+        args.paths
+    };
+
+    for path in &paths {
+        let mut hasher = PathHasher::new();
+        hasher.write_str(path);
+        println!(
+            "0x{:016X}\t0x{:08X}\t0x{:08X}\t{path}",
+            hasher.finish(),
+            hasher.finish_lower(),
+            hasher.finish_upper()
+        );
+    }
+
+    Ok(())
+}
+
+/// Non-empty, trimmed lines of stdin.
+pub(super) fn read_stdin_lines() -> eyre::Result<Vec<String>> {
+    let mut lines = vec![];
+    for line in io::stdin().lock().lines() {
+        let line = line?;
+        let line = line.trim();
+        if !line.is_empty() {
+            lines.push(line.to_string());
+        }
+    }
+    Ok(lines)
+}
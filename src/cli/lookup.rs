@@ -0,0 +1,69 @@
+use clap::Args;
+use color_eyre::eyre;
+use ree_path_searcher::hash::{hash_path, parse_hash};
+use rustc_hash::FxHashMap;
+
+use super::{CommonArgs, PakArgs, hash::read_stdin_lines, load_path_list};
+
+#[derive(Debug, Args)]
+pub struct LookupArgs {
+    /// Hashes to look up, as `0x`-prefixed hex, 16 hex digits or decimal. Read from stdin, one
+    /// per line, when omitted.
+    hashes: Vec<String>,
+    /// Path lists searched for paths with the given hashes.
+    #[arg(long)]
+    list: Vec<String>,
+    #[command(flatten)]
+    paks: PakArgs,
+    #[command(flatten)]
+    common: CommonArgs,
+}
+
+pub fn run(args: LookupArgs) -> eyre::Result<()> {
+    if args.list.is_empty() && args.paks.is_empty() {
+        eyre::bail!(
+            "lookup requires path lists or input PAKs. Use --list, --game-dir, --pak or --pak-list."
+        );
+    }
+
+    let inputs = if args.hashes.is_empty() {
+        read_stdin_lines()?
+    } else {
+        args.hashes
+    };
+    let hashes = inputs
+        .iter()
+        .map(|s| parse_hash(s).ok_or_else(|| eyre::eyre!("Invalid hash: {s}")))
+        .collect::<eyre::Result<Vec<u64>>>()?;
+
+    let mut paths_by_hash: FxHashMap<u64, String> = FxHashMap::default();
+    for list in &args.list {
+        for path in load_path_list(list)? {
+            paths_by_hash.entry(hash_path(&path)).or_insert(path);
+        }
+    }
+
+    let searcher_config = args.common.load_config()?;
+    args.common.init_thread_pool()?;
+    let searcher = args.paks.build_searcher(searcher_config)?;
+
+    for hash in hashes {
+        let path = paths_by_hash.get(&hash).map_or("<unknown>", String::as_str);
+        print!("0x{hash:016X}\t{path}");
+        if let Some(pak_collection) = searcher.pak_collection() {
+            let paks = pak_collection.paks_for_hash(hash);
+            if paks.is_empty() {
+                print!("\t<not in PAKs>");
+            } else {
+                let names: Vec<&str> = paks
+                    .iter()
+                    .map(|&i| pak_collection.pak_names()[i].as_str())
+                    .collect();
+                print!("\t{}", names.join(", "));
+            }
+        }
+        println!();
+    }
+
+    Ok(())
+}
@@ -0,0 +1,117 @@
+use std::path::PathBuf;
+
+use clap::Args;
+use color_eyre::eyre;
+use ree_path_searcher::export;
+use ree_path_searcher::overrides::{ModEntry, OverrideReport};
+use rustc_hash::FxHashSet;
+
+use super::{CommonArgs, PakArgs, diff, load_pak_list, load_path_list, report_pak_loading};
+
+#[derive(Debug, Args)]
+pub struct OverridesArgs {
+    #[command(flatten)]
+    paks: PakArgs,
+    /// Mod PAK files, loaded after the base game in the given order.
+    #[arg(long)]
+    mod_pak: Vec<String>,
+    /// A list of mod PAK files, loaded after `--mod-pak`.
+    #[arg(long)]
+    mod_pak_list: Option<String>,
+    /// Path lists naming the entries. Entries missing from them are reported by hash.
+    #[arg(long)]
+    list: Vec<String>,
+    #[command(flatten)]
+    common: CommonArgs,
+    /// Directory `overrides.list`, `added.list` and `conflicts.list` are written to.
+    #[arg(long, default_value = ".")]
+    output_dir: PathBuf,
+}
+
+pub fn run(args: OverridesArgs) -> eyre::Result<()> {
+    let mut mod_paks = args.mod_pak.clone();
+    if let Some(list) = &args.mod_pak_list {
+        mod_paks.extend(load_pak_list(list)?);
+    }
+    if args.paks.is_empty() || mod_paks.is_empty() {
+        eyre::bail!(
+            "overrides requires base and mod PAKs. Use --game-dir/--pak/--pak-list and --mod-pak/--mod-pak-list."
+        );
+    }
+
+    let searcher_config = args.common.load_config()?;
+    args.common.init_thread_pool()?;
+
+    let searcher = args
+        .paks
+        .searcher_builder(searcher_config)?
+        .with_pak_paths(&mod_paks)
+        .build()?;
+    report_pak_loading(&searcher);
+    let Some(pak_collection) = searcher.pak_collection() else {
+        return Ok(());
+    };
+
+    // Mods come last, so the first loaded mod starts them even when some PAKs were skipped.
+    let mod_names: FxHashSet<&str> = mod_paks.iter().map(String::as_str).collect();
+    let first_mod = pak_collection
+        .pak_names()
+        .iter()
+        .position(|name| mod_names.contains(name.as_str()))
+        .unwrap_or(pak_collection.pak_files().len());
+
+    let mut lines: Vec<String> = vec![];
+    for list in &args.list {
+        lines.extend(load_path_list(list)?);
+    }
+    eprintln!("Resolving {} paths..", lines.len());
+    let full_paths = diff::resolve_all(&searcher, &lines);
+
+    let report = OverrideReport::new(pak_collection, first_mod, &full_paths);
+    let names = pak_collection.pak_names();
+    let pak_names = |indices: &[usize]| -> String {
+        indices
+            .iter()
+            .map(|&i| names[i].as_str())
+            .collect::<Vec<_>>()
+            .join(", ")
+    };
+    let display = |entry: &ModEntry| match &entry.full_path {
+        Some(path) => path.clone(),
+        None => format!("0x{:016X}", entry.hash),
+    };
+
+    let overridden: Vec<String> = report
+        .overridden()
+        .map(|e| {
+            let winner = &names[e.winner()];
+            format!("{}\t{winner}\t{}", display(e), pak_names(&e.shadowed))
+        })
+        .collect();
+    let added: Vec<String> = report
+        .added()
+        .map(|e| format!("{}\t{}", display(e), names[e.winner()]))
+        .collect();
+    let conflicts: Vec<String> = report
+        .conflicts()
+        .map(|e| format!("{}\t{}", display(e), pak_names(&e.mods)))
+        .collect();
+    let unnamed = report
+        .entries
+        .iter()
+        .filter(|e| e.full_path.is_none())
+        .count();
+
+    println!(
+        "Overridden: {}, added: {}, conflicts: {}, unnamed: {unnamed}",
+        overridden.len(),
+        added.len(),
+        conflicts.len()
+    );
+
+    export::write_list(&args.output_dir, "overrides.list", &overridden)?;
+    export::write_list(&args.output_dir, "added.list", &added)?;
+    export::write_list(&args.output_dir, "conflicts.list", &conflicts)?;
+
+    Ok(())
+}
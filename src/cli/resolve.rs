@@ -0,0 +1,120 @@
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Instant;
+
+use clap::Args;
+use color_eyre::eyre;
+use dashmap::DashSet;
+use parking_lot::Mutex;
+use rayon::prelude::*;
+use ree_pak_core::PakReader;
+use ree_path_searcher::export;
+use ree_path_searcher::{PathSearcher, SearchResult};
+
+use super::{CommonArgs, OutputArgs, PakArgs, load_path_list, progress_bar};
+
+#[derive(Debug, Args)]
+pub struct ResolveArgs {
+    #[command(flatten)]
+    paks: PakArgs,
+    /// Reference path lists. Each line is a raw or full path to resolve in input PAKs.
+    #[arg(long, required = true)]
+    ref_list: Vec<String>,
+    #[command(flatten)]
+    common: CommonArgs,
+    #[command(flatten)]
+    output: OutputArgs,
+}
+
+pub fn run(args: ResolveArgs) -> eyre::Result<()> {
+    if args.paks.is_empty() {
+        eyre::bail!("resolve requires input PAKs. Use --game-dir, --pak or --pak-list.");
+    }
+
+    let searcher_config = args.common.load_config()?;
+    args.common.init_thread_pool()?;
+
+    let start = Instant::now();
+    let searcher = args.paks.build_searcher(searcher_config.clone())?;
+    println!("Input pak total file count: {}", searcher.pak_file_count());
+
+    let matched = resolve_reference_lists(&searcher, &args.ref_list)?;
+
+    println!("Exporting results..");
+    export::export_results(
+        &args.output.output_dir,
+        args.output.format.into(),
+        &SearchResult::default(),
+        &matched,
+        &searcher_config,
+        searcher.pak_collection(),
+    )?;
+
+    let elapsed = start.elapsed();
+    println!("Elapsed: {:.2?} seconds", elapsed.as_secs_f32());
+
+    Ok(())
+}
+
+fn canonicalize_ref_path(path: &str) -> String {
+    path.trim().replace('\\', "/")
+}
+
+/// Resolve every line of `ref_lists` and return the sorted, matched full paths.
+pub fn resolve_reference_lists<R: PakReader>(
+    searcher: &PathSearcher<R>,
+    ref_lists: &[String],
+) -> eyre::Result<Vec<String>> {
+    let mut refs: Vec<String> = vec![];
+    for file in ref_lists {
+        refs.extend(load_path_list(file)?);
+    }
+
+    eprintln!("Resolving reference list..");
+    let progress_bar = progress_bar(refs.len() as u64);
+
+    let matched: DashSet<String> = DashSet::default();
+    let missing_count = AtomicUsize::new(0);
+    let error_count = AtomicUsize::new(0);
+    let first_errors: Mutex<Vec<(String, String)>> = Mutex::new(vec![]);
+
+    let pb = progress_bar.clone();
+    refs.par_iter().for_each(|r| {
+        match searcher.resolve_reference_line(r) {
+            Ok(infos) if !infos.is_empty() => {
+                for info in infos {
+                    matched.insert(canonicalize_ref_path(&info.full_path));
+                }
+            }
+            Ok(_) => {
+                missing_count.fetch_add(1, Ordering::Relaxed);
+            }
+            Err(err) => {
+                error_count.fetch_add(1, Ordering::Relaxed);
+                let mut guard = first_errors.lock();
+                if guard.len() < 5 {
+                    guard.push((r.clone(), format!("{err:#}")));
+                }
+            }
+        }
+        pb.inc(1);
+    });
+    progress_bar.finish_with_message("Resolve reference list finished.");
+
+    let mut matched: Vec<String> = matched.into_iter().collect();
+    matched.sort_unstable();
+    eprintln!(
+        "Reference list: matched {} paths, missing {}, errors {}.",
+        matched.len(),
+        missing_count.load(Ordering::Relaxed),
+        error_count.load(Ordering::Relaxed)
+    );
+    let first_errors = first_errors.into_inner();
+    if !first_errors.is_empty() {
+        eprintln!("Reference list: first {} errors:", first_errors.len());
+        for (line, err) in first_errors {
+            eprintln!("  - {line}: {err}");
+        }
+    }
+
+    Ok(matched)
+}
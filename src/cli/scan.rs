@@ -0,0 +1,308 @@
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+
+use clap::{ArgGroup, Args};
+use color_eyre::eyre;
+use indicatif::ProgressBar;
+use ree_pak_core::PakReader;
+use ree_path_searcher::export;
+use ree_path_searcher::extract::{self, ExtractFilter};
+use ree_path_searcher::{PathSearcherBuilder, PathSearcherConfig, ProgressCallback, SearchResult};
+
+use super::{
+    CommonArgs, OutputArgs, PakArgs, load_path_list, mib_to_bytes, progress_bar,
+    report_pak_loading, resolve,
+};
+
+#[derive(Debug, Args)]
+#[command(group(ArgGroup::new("extraction").args(["extract", "extract_dry_run"]).multiple(true)))]
+pub struct ScanArgs {
+    #[command(flatten)]
+    paks: PakArgs,
+    /// Paths to dmp files.
+    #[arg(short, long)]
+    dmp: Vec<String>,
+    /// Reference path lists. Each line is a reference path to check in input PAKs.
+    #[arg(long)]
+    ref_list: Vec<String>,
+    /// Lists of already known full paths. Raw paths they cover are not resolved again, and only
+    /// resolved paths missing from them are written to `new.list`.
+    #[arg(long)]
+    baseline: Vec<String>,
+    /// Resolve the raw paths of the baseline again, so new language, platform or version
+    /// variants of known files are found too. Slower with large baselines.
+    #[arg(long, requires = "baseline")]
+    resolve_baseline_variants: bool,
+    /// File resolutions are kept in across runs. It is ignored and rewritten when the PAKs or the
+    /// config change.
+    #[arg(long)]
+    cache: Option<PathBuf>,
+    /// File the strings found in each PAK entry are kept in, so later scans only extract entries
+    /// that changed.
+    #[arg(long)]
+    scan_state: Option<PathBuf>,
+    /// Rough cap in MiB on decompressed PAK data held at once. Entries too large to share it
+    /// with every thread are scanned one at a time.
+    #[arg(long, value_name = "MIB")]
+    memory_budget: Option<u64>,
+    /// PAK entries larger than this many MiB once decompressed are not scanned.
+    #[arg(long, value_name = "MIB")]
+    max_entry_size: Option<u64>,
+    /// Scan uncompressed entries above --max-entry-size in chunks instead of skipping them.
+    #[arg(long, requires = "max_entry_size")]
+    stream_large_entries: bool,
+    /// Stop the PAK scan once this many distinct raw paths were found. Formats that usually
+    /// reference many paths are scanned first when their format is known: from --scan-state,
+    /// --baseline or --cache, or from the header of uncompressed entries.
+    #[arg(long, value_name = "COUNT")]
+    stop_after_paths: Option<usize>,
+    /// Stop the PAK scan after this many seconds.
+    #[arg(long, value_name = "SECONDS")]
+    time_budget: Option<u64>,
+    /// Memory-map the PAK files. Entries stored without compression are then scanned straight
+    /// from the mapping instead of being extracted.
+    #[arg(long)]
+    mmap: bool,
+    /// Extract the files of the resolved paths into this directory, each from the PAK the game
+    /// loads it from.
+    #[arg(long, value_name = "DIR")]
+    extract: Option<PathBuf>,
+    /// Only extract files with this extension, e.g. `tex`.
+    #[arg(long, value_name = "EXT", requires = "extraction")]
+    extract_ext: Vec<String>,
+    /// Only extract files whose full path matches this glob. `*` also matches `/`.
+    #[arg(long, value_name = "GLOB", requires = "extraction")]
+    extract_glob: Vec<String>,
+    /// Write the files that would be extracted to `extract.list` in the output directory instead
+    /// of extracting them. Needs no --extract.
+    #[arg(long)]
+    extract_dry_run: bool,
+    #[command(flatten)]
+    common: CommonArgs,
+    #[command(flatten)]
+    output: OutputArgs,
+}
+
+pub fn run(args: ScanArgs) -> eyre::Result<()> {
+    if args.paks.is_empty() && args.dmp.is_empty() && args.ref_list.is_empty() {
+        eprintln!(
+            "Error: No PAK/DMP/reference list specified. Use --game-dir, --pak, --pak-list, --dmp, or --ref-list."
+        );
+        std::process::exit(1);
+    }
+
+    if !args.ref_list.is_empty() && args.paks.is_empty() {
+        eprintln!("Error: --ref-list requires input PAKs. Use --game-dir, --pak or --pak-list.");
+        std::process::exit(1);
+    }
+
+    let searcher_config = args.common.load_config()?;
+    args.common.init_thread_pool()?;
+
+    if args.mmap {
+        let builder = args.paks.mapped_searcher_builder(searcher_config.clone())?;
+        scan(&args, searcher_config, builder)
+    } else {
+        let builder = args.paks.searcher_builder(searcher_config.clone())?;
+        scan(&args, searcher_config, builder)
+    }
+}
+
+fn scan<R: PakReader>(
+    args: &ScanArgs,
+    searcher_config: PathSearcherConfig,
+    builder: PathSearcherBuilder<R>,
+) -> eyre::Result<()> {
+    let start = Instant::now();
+
+    let mut baseline = vec![];
+    for list in &args.baseline {
+        baseline.extend(load_path_list(list)?);
+    }
+    if !args.baseline.is_empty() {
+        eprintln!("Baseline: {} known paths", baseline.len());
+    }
+
+    let mut builder = builder
+        .with_baseline(baseline)
+        .with_resolve_baseline_variants(args.resolve_baseline_variants);
+    if let Some(cache) = &args.cache {
+        builder = builder.with_cache_file(cache);
+    }
+    if let Some(scan_state) = &args.scan_state {
+        builder = builder.with_scan_state_file(scan_state);
+    }
+    if let Some(budget) = args.memory_budget {
+        builder = builder.with_memory_budget(mib_to_bytes("--memory-budget", budget)?);
+    }
+    if let Some(max_size) = args.max_entry_size {
+        builder = builder
+            .with_max_entry_size(mib_to_bytes("--max-entry-size", max_size)?)
+            .with_stream_large_entries(args.stream_large_entries);
+    }
+    if let Some(count) = args.stop_after_paths {
+        builder = builder.with_stop_after_paths(count);
+    }
+    if let Some(seconds) = args.time_budget {
+        builder = builder.with_scan_time_budget(Duration::from_secs(seconds));
+    }
+    let searcher = builder.build()?;
+    report_pak_loading(&searcher);
+    if args.cache.is_some() {
+        eprintln!(
+            "Resolution cache: {} raw paths",
+            searcher.cached_path_count()
+        );
+    }
+
+    if !args.paks.is_empty() {
+        println!("Input pak total file count: {}", searcher.pak_file_count());
+    }
+
+    let mut all_results = SearchResult::default();
+
+    for dmp in &args.dmp {
+        eprintln!("Scanning {dmp}..");
+        let progress_bar = progress_bar(100);
+
+        let result =
+            searcher.search_memory_dump_with_progress(dmp, |current: u64, total: u64| {
+                progress_bar.set_length(total);
+                progress_bar.set_position(current);
+            })?;
+
+        progress_bar.finish_with_message("Scan dump finished.");
+        all_results.found_paths.extend(result.found_paths);
+        all_results.unknown_paths.extend(result.unknown_paths);
+        all_results.new_paths.extend(result.new_paths);
+    }
+
+    if searcher.pak_file_count() != 0 {
+        eprintln!("Scanning all PAK files..");
+        let progress_bar = progress_bar(searcher.pak_file_count() as u64);
+
+        let result = searcher.search_pak_files_with_progress(PakScanProgress(&progress_bar))?;
+
+        if result.stopped_early {
+            progress_bar.abandon_with_message("Scan pak files stopped early.");
+        } else {
+            progress_bar.finish_with_message("Scan pak files finished.");
+        }
+        all_results.found_paths.extend(result.found_paths);
+        all_results.unknown_paths.extend(result.unknown_paths);
+        all_results.new_paths.extend(result.new_paths);
+        all_results.entry_errors.extend(result.entry_errors);
+    }
+
+    // resolve reference list if provided
+    let mut ref_matched_full_paths: Vec<String> = vec![];
+    if !args.ref_list.is_empty() {
+        if searcher.pak_collection().is_some() {
+            ref_matched_full_paths = resolve::resolve_reference_lists(&searcher, &args.ref_list)?;
+        } else {
+            eprintln!(
+                "Warning: --ref-list provided but no PAK files loaded; skipping reference checks."
+            );
+        }
+    }
+
+    println!("Sorting results..");
+    all_results
+        .found_paths
+        .sort_unstable_by(|(p, _), (q, _)| p.cmp(q));
+    all_results.found_paths.dedup_by(|(p, _), (q, _)| p == q);
+    all_results.new_paths.extend(
+        ref_matched_full_paths
+            .iter()
+            .filter(|path| !searcher.is_known(path))
+            .cloned(),
+    );
+    all_results.new_paths.sort_unstable();
+    all_results.new_paths.dedup();
+
+    println!("Exporting results..");
+    export::export_results(
+        &args.output.output_dir,
+        args.output.format.into(),
+        &all_results,
+        &ref_matched_full_paths,
+        &searcher_config,
+        searcher.pak_collection(),
+    )?;
+    if !args.baseline.is_empty() {
+        println!("New paths: {}", all_results.new_paths.len());
+        export::write_list(&args.output.output_dir, "new.list", &all_results.new_paths)?;
+    }
+
+    if !all_results.entry_errors.is_empty()
+        && let Some(pak_collection) = searcher.pak_collection()
+    {
+        let names = pak_collection.pak_names();
+        eprintln!(
+            "Warning: {} PAK entries could not be scanned; see entry_errors.list.",
+            all_results.entry_errors.len()
+        );
+        export::write_list(
+            &args.output.output_dir,
+            "entry_errors.list",
+            all_results
+                .entry_errors
+                .iter()
+                .map(|e| format!("{:016x}\t{}\t{}", e.hash, names[e.pak_index], e.message)),
+        )?;
+    }
+
+    if (args.extract.is_some() || args.extract_dry_run)
+        && let Some(pak_collection) = searcher.pak_collection()
+    {
+        let filter = ExtractFilter::default()
+            .with_extensions(args.extract_ext.iter().cloned())
+            .with_globs(args.extract_glob.iter().cloned());
+        let full_paths = all_results
+            .full_paths()
+            .chain(ref_matched_full_paths.iter().map(String::as_str));
+        let planned =
+            extract::plan_extraction(pak_collection, full_paths, &filter, &searcher_config);
+        if args.extract_dry_run {
+            println!("Files to extract: {}", planned.len());
+            export::write_list(
+                &args.output.output_dir,
+                "extract.list",
+                planned.iter().map(|file| {
+                    format!(
+                        "{}\t{}\t{}",
+                        file.full_path, file.entry.pak_name, file.entry.decompressed_size
+                    )
+                }),
+            )?;
+        } else if let Some(extract_dir) = &args.extract {
+            println!("Extracting {} files..", planned.len());
+            let summary = extract::extract_files(pak_collection, &planned, extract_dir)?;
+            println!("Extracted: {}", summary.written);
+            for (path, error) in &summary.failed {
+                eprintln!("Warning: failed to extract {path}: {error}");
+            }
+        }
+    }
+
+    searcher.save_cache()?;
+
+    let elapsed = start.elapsed();
+    println!("Elapsed: {:.2?} seconds", elapsed.as_secs_f32());
+
+    Ok(())
+}
+
+/// Progress bar updates for PAK scans, showing the paths found so far.
+struct PakScanProgress<'a>(&'a ProgressBar);
+
+impl ProgressCallback for PakScanProgress<'_> {
+    fn on_progress(&self, current: u64, total: u64) {
+        self.0.set_length(total);
+        self.0.set_position(current);
+    }
+
+    fn on_discovery(&self, found: u64) {
+        self.0.set_message(format!("{found} paths found"));
+    }
+}
@@ -0,0 +1,87 @@
+use clap::Args;
+use color_eyre::eyre;
+use rayon::prelude::*;
+use ree_path_searcher::hash::hash_path;
+use rustc_hash::FxHashSet;
+
+use super::{CommonArgs, PakArgs, load_path_list};
+
+#[derive(Debug, Args)]
+pub struct StatsArgs {
+    #[command(flatten)]
+    paks: PakArgs,
+    /// Path lists whose coverage of the PAK entries is reported.
+    #[arg(long)]
+    list: Vec<String>,
+    #[command(flatten)]
+    common: CommonArgs,
+}
+
+pub fn run(args: StatsArgs) -> eyre::Result<()> {
+    if args.paks.is_empty() {
+        eyre::bail!("stats requires input PAKs. Use --game-dir, --pak or --pak-list.");
+    }
+
+    let searcher_config = args.common.load_config()?;
+    args.common.init_thread_pool()?;
+
+    let searcher = args.paks.build_searcher(searcher_config)?;
+    let Some(pak_collection) = searcher.pak_collection() else {
+        return Ok(());
+    };
+
+    let mut total_entries = 0;
+    println!("PAK files: {}", pak_collection.pak_files().len());
+    for (name, pak) in pak_collection
+        .pak_names()
+        .iter()
+        .zip(pak_collection.pak_files())
+    {
+        let entries = pak.metadata().entries().len();
+        total_entries += entries;
+        println!("  {name}: {entries} entries");
+    }
+    let unique = pak_collection.unique_entry_count();
+    println!("Total entries: {total_entries}");
+    println!("Unique entries: {unique}");
+    println!("Shadowed by later PAKs: {}", total_entries - unique);
+
+    let mut named_all = FxHashSet::default();
+    for list in &args.list {
+        let lines = load_path_list(list)?;
+        let named: FxHashSet<u64> = lines
+            .par_iter()
+            .flat_map_iter(|line| {
+                searcher
+                    .resolve_reference_line(line)
+                    .unwrap_or_default()
+                    .into_iter()
+                    .map(|info| hash_path(&info.full_path))
+            })
+            .collect();
+        println!(
+            "{list}: {} lines, {} entries named ({:.2}%)",
+            lines.len(),
+            named.len(),
+            percent(named.len(), unique)
+        );
+        named_all.extend(named);
+    }
+    if args.list.len() > 1 {
+        println!(
+            "All lists: {} entries named ({:.2}%)",
+            named_all.len(),
+            percent(named_all.len(), unique)
+        );
+    }
+
+    Ok(())
+}
+
+fn percent(part: usize, total: usize) -> f64 {
+    if total == 0 {
+        0.0
+    } else {
+        part as f64 * 100.0 / total as f64
+    }
+}
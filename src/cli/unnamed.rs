@@ -0,0 +1,100 @@
+use std::path::PathBuf;
+
+use clap::Args;
+use color_eyre::eyre;
+use ree_path_searcher::export;
+
+use super::{CommonArgs, PakArgs, diff, load_path_list, mib_to_bytes, report_pak_loading};
+
+#[derive(Debug, Args)]
+pub struct UnnamedArgs {
+    #[command(flatten)]
+    paks: PakArgs,
+    /// Path lists naming the entries. The entries none of them name are classified.
+    #[arg(long)]
+    list: Vec<String>,
+    /// Scan state file of an earlier scan. The formats it recorded are reused instead of
+    /// extracting the entries again.
+    #[arg(long)]
+    scan_state: Option<PathBuf>,
+    /// Entries larger than this many MiB once decompressed are not extracted to sniff them.
+    /// Defaults to 64. Uncompressed entries only have their header read, whatever their size.
+    #[arg(long, value_name = "MIB")]
+    max_entry_size: Option<u64>,
+    /// Lists of guessed paths without extension, e.g. `natives/STM/GUI/ui010101`. Each is tried
+    /// with the extensions unnamed entries were sniffed as.
+    #[arg(long)]
+    guess_list: Vec<String>,
+    #[command(flatten)]
+    common: CommonArgs,
+    /// Directory `unnamed.list` and `guessed.list` are written to.
+    #[arg(long, default_value = ".")]
+    output_dir: PathBuf,
+}
+
+pub fn run(args: UnnamedArgs) -> eyre::Result<()> {
+    if args.paks.is_empty() {
+        eyre::bail!("unnamed requires input PAKs. Use --game-dir, --pak or --pak-list.");
+    }
+
+    let searcher_config = args.common.load_config()?;
+    args.common.init_thread_pool()?;
+
+    let mut builder = args.paks.searcher_builder(searcher_config)?;
+    if let Some(scan_state) = &args.scan_state {
+        builder = builder.with_scan_state_file(scan_state);
+    }
+    if let Some(max_size) = args.max_entry_size {
+        builder = builder.with_max_entry_size(mib_to_bytes("--max-entry-size", max_size)?);
+    }
+    let searcher = builder.build()?;
+    report_pak_loading(&searcher);
+
+    let mut lines: Vec<String> = vec![];
+    for list in &args.list {
+        lines.extend(load_path_list(list)?);
+    }
+    eprintln!("Resolving {} paths..", lines.len());
+    let full_paths = diff::resolve_all(&searcher, &lines);
+
+    eprintln!("Classifying unnamed entries..");
+    let unnamed = searcher.unnamed_entries(&full_paths)?;
+    let by_extension = unnamed.by_extension();
+    let mut unclassified: Vec<u64> = unnamed.unclassified().collect();
+    unclassified.sort_unstable();
+
+    println!(
+        "Unnamed: {}, unknown format: {}",
+        unnamed.len(),
+        unclassified.len()
+    );
+    for (extension, hashes) in &by_extension {
+        println!("  {extension}: {}", hashes.len());
+    }
+
+    export::write_list(
+        &args.output_dir,
+        "unnamed.list",
+        by_extension
+            .iter()
+            .flat_map(|(extension, hashes)| {
+                hashes
+                    .iter()
+                    .map(move |hash| format!("{hash:016x}\t{extension}"))
+            })
+            .chain(unclassified.iter().map(|hash| format!("{hash:016x}\t?"))),
+    )?;
+
+    if !args.guess_list.is_empty() {
+        let mut stems: Vec<String> = vec![];
+        for list in &args.guess_list {
+            stems.extend(load_path_list(list)?);
+        }
+        eprintln!("Trying {} guesses..", stems.len());
+        let guessed = searcher.guess_unnamed(&stems, &unnamed);
+        println!("Guessed: {}", guessed.len());
+        export::write_list(&args.output_dir, "guessed.list", &guessed)?;
+    }
+
+    Ok(())
+}
@@ -0,0 +1,108 @@
+use std::path::PathBuf;
+
+use clap::Args;
+use color_eyre::eyre;
+use rayon::prelude::*;
+use ree_path_searcher::{PathComponents, export};
+
+use super::{CommonArgs, PakArgs, load_path_list, progress_bar};
+
+#[derive(Debug, Args)]
+pub struct VerifyArgs {
+    #[command(flatten)]
+    paks: PakArgs,
+    /// Path lists to verify. Each line is a full path expected in input PAKs.
+    #[arg(long, required = true)]
+    list: Vec<String>,
+    #[command(flatten)]
+    common: CommonArgs,
+    /// Directory `verified.list`, `changed.list` and `missing.list` are written to.
+    #[arg(long, default_value = ".")]
+    output_dir: PathBuf,
+}
+
+enum LineStatus {
+    /// Present as written, once normalized.
+    Present(String),
+    /// Not present as written, but resolved to these paths, e.g. under another version.
+    Changed(Vec<String>),
+    Missing,
+}
+
+pub fn run(args: VerifyArgs) -> eyre::Result<()> {
+    if args.paks.is_empty() {
+        eyre::bail!("verify requires input PAKs. Use --game-dir, --pak or --pak-list.");
+    }
+
+    let searcher_config = args.common.load_config()?;
+    args.common.init_thread_pool()?;
+
+    let searcher = args.paks.build_searcher(searcher_config)?;
+    let Some(pak_collection) = searcher.pak_collection() else {
+        return Ok(());
+    };
+
+    let mut lines: Vec<String> = vec![];
+    for list in &args.list {
+        lines.extend(load_path_list(list)?);
+    }
+
+    eprintln!("Verifying {} paths..", lines.len());
+    let progress_bar = progress_bar(lines.len() as u64);
+    let statuses: Vec<LineStatus> = lines
+        .par_iter()
+        .map(|line| {
+            let normalized = PathComponents::parse(line, searcher.config())
+                .map(PathComponents::into_normalized_full_path);
+            let status = if let Some(normalized) = normalized
+                && pak_collection.contains_path(&normalized)
+            {
+                LineStatus::Present(normalized)
+            } else {
+                match searcher.resolve_reference_line(line) {
+                    Ok(infos) if !infos.is_empty() => {
+                        LineStatus::Changed(infos.into_iter().map(|i| i.full_path).collect())
+                    }
+                    _ => LineStatus::Missing,
+                }
+            };
+            progress_bar.inc(1);
+            status
+        })
+        .collect();
+    progress_bar.finish_with_message("Verify finished.");
+
+    let mut verified = vec![];
+    let mut changed = vec![];
+    let mut missing = vec![];
+    let (mut present_count, mut changed_count) = (0, 0);
+    for (line, status) in lines.iter().zip(statuses) {
+        match status {
+            LineStatus::Present(normalized) => {
+                present_count += 1;
+                verified.push(normalized);
+            }
+            LineStatus::Changed(full_paths) => {
+                changed_count += 1;
+                for full_path in full_paths {
+                    changed.push(format!("{line}\t{full_path}"));
+                    verified.push(full_path);
+                }
+            }
+            LineStatus::Missing => missing.push(line.as_str()),
+        }
+    }
+    verified.sort_unstable();
+    verified.dedup();
+
+    println!(
+        "Present: {present_count}, changed: {changed_count}, missing: {}",
+        missing.len()
+    );
+
+    export::write_list(&args.output_dir, "verified.list", &verified)?;
+    export::write_list(&args.output_dir, "changed.list", &changed)?;
+    export::write_list(&args.output_dir, "missing.list", &missing)?;
+
+    Ok(())
+}
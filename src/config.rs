@@ -1,16 +1,29 @@
+use std::collections::BTreeMap;
 use std::fs;
+use std::hash::Hasher;
 use std::path::Path;
 use std::sync::Arc;
 
 use color_eyre::eyre::{self, Context};
-use rustc_hash::FxHashMap;
-use serde::Deserialize;
+use rustc_hash::{FxHashMap, FxHasher};
+use serde::{Deserialize, Serialize};
+
+mod template;
+
+pub use template::{
+    CandidateTemplate, DEFAULT_CANDIDATE_TEMPLATE, SlotKind, TemplateSegment, TemplateSlot,
+};
 
 #[derive(Debug, Clone)]
 pub struct PathSearcherConfig {
     languages: Arc<[String]>,
     prefixes: Arc<[String]>,
     platform_suffixes: Arc<[String]>,
+    prefix_platform_suffixes: Arc<FxHashMap<String, Vec<String>>>,
+    platform_tags: Arc<[String]>,
+    arch_tags: Arc<[String]>,
+    candidate_templates: Arc<[CandidateTemplate]>,
+    match_all_versions: bool,
     suffix_map: Arc<FxHashMap<String, Vec<u32>>>,
 }
 
@@ -19,6 +32,13 @@ struct PathSearcherConfigFile {
     pub languages: Option<Vec<String>>,
     pub prefixes: Option<Vec<String>>,
     pub platform_suffixes: Option<Vec<String>>,
+    #[serde(default)]
+    pub prefix_platform_suffixes: FxHashMap<String, Vec<String>>,
+    pub platform_tags: Option<Vec<String>>,
+    pub arch_tags: Option<Vec<String>>,
+    pub candidate_templates: Option<Vec<String>>,
+    #[serde(default)]
+    pub match_all_versions: bool,
     #[serde(default = "default_true")]
     pub use_builtin_suffix_map: bool,
     #[serde(default)]
@@ -30,12 +50,33 @@ struct PathSearcherConfigFile {
     pub suffix_map_full: FxHashMap<String, Vec<u32>>,
 }
 
+/// Serialized form of [`PathSearcherConfig`]; plain values come before tables as TOML requires.
+#[derive(Serialize)]
+struct PathSearcherConfigOut<'a> {
+    languages: &'a [String],
+    prefixes: &'a [String],
+    platform_suffixes: &'a [String],
+    platform_tags: &'a [String],
+    arch_tags: &'a [String],
+    candidate_templates: Vec<&'a str>,
+    match_all_versions: bool,
+    use_builtin_suffix_map: bool,
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    prefix_platform_suffixes: BTreeMap<&'a str, &'a [String]>,
+    suffix_map: BTreeMap<&'a str, &'a [u32]>,
+}
+
 impl Default for PathSearcherConfig {
     fn default() -> Self {
         Self {
             languages: default_languages().into(),
             prefixes: default_prefixes().into(),
             platform_suffixes: default_platform_suffixes().into(),
+            prefix_platform_suffixes: Arc::new(FxHashMap::default()),
+            platform_tags: default_platform_tags().into(),
+            arch_tags: default_arch_tags().into(),
+            candidate_templates: default_candidate_templates().into(),
+            match_all_versions: false,
             suffix_map: Arc::new(default_suffix_map_full_owned()),
         }
     }
@@ -67,10 +108,88 @@ impl PathSearcherConfig {
         &self.platform_suffixes
     }
 
+    /// Suffix tags valid under `prefix`, falling back to [`Self::platform_suffixes`] when the
+    /// prefix has no entry in `prefix_platform_suffixes`.
+    pub fn platform_suffixes_for_prefix(&self, prefix: Option<&str>) -> &[String] {
+        prefix
+            .and_then(|p| self.prefix_platform_suffixes.get(p))
+            .map(Vec::as_slice)
+            .unwrap_or(&self.platform_suffixes)
+    }
+
+    /// Tags recognized as the platform part of a path tail, e.g. `STM` in `.tex.123.STM`.
+    pub fn platform_tags(&self) -> &[String] {
+        &self.platform_tags
+    }
+
+    /// Tags recognized as the architecture part of a path tail, e.g. `X64` in `.tex.123.X64`.
+    pub fn arch_tags(&self) -> &[String] {
+        &self.arch_tags
+    }
+
+    /// Templates driving full-path candidate generation, tried in order.
+    pub fn candidate_templates(&self) -> &[CandidateTemplate] {
+        &self.candidate_templates
+    }
+
+    /// Whether every matching version is collected instead of only the newest one.
+    pub fn match_all_versions(&self) -> bool {
+        self.match_all_versions
+    }
+
+    pub fn with_match_all_versions(mut self, enabled: bool) -> Self {
+        self.match_all_versions = enabled;
+        self
+    }
+
     pub fn suffix_versions(&self, extension: &str) -> Option<&[u32]> {
         self.suffix_map.get(extension).map(Vec::as_slice)
     }
 
+    /// Extensions of the suffix map, in no particular order.
+    pub fn suffix_extensions(&self) -> impl Iterator<Item = &str> {
+        self.suffix_map.keys().map(String::as_str)
+    }
+
+    /// Render the effective config as TOML, with the full suffix map spelled out so the output
+    /// loads back into the same config.
+    pub fn to_toml_string(&self) -> eyre::Result<String> {
+        let out = PathSearcherConfigOut {
+            languages: &self.languages,
+            prefixes: &self.prefixes,
+            platform_suffixes: &self.platform_suffixes,
+            platform_tags: &self.platform_tags,
+            arch_tags: &self.arch_tags,
+            candidate_templates: self
+                .candidate_templates
+                .iter()
+                .map(|t| t.as_str())
+                .collect(),
+            match_all_versions: self.match_all_versions,
+            use_builtin_suffix_map: false,
+            prefix_platform_suffixes: self
+                .prefix_platform_suffixes
+                .iter()
+                .map(|(k, v)| (k.as_str(), v.as_slice()))
+                .collect(),
+            suffix_map: self
+                .suffix_map
+                .iter()
+                .map(|(k, v)| (k.as_str(), v.as_slice()))
+                .collect(),
+        };
+        Ok(toml::to_string(&out)?)
+    }
+
+    /// Hash of [`Self::to_toml_string`], stable across runs. Configs with the same fingerprint
+    /// resolve paths identically.
+    pub fn fingerprint(&self) -> u64 {
+        let toml = self.to_toml_string().unwrap_or_default();
+        let mut hasher = FxHasher::default();
+        hasher.write(toml.as_bytes());
+        hasher.finish()
+    }
+
     fn from_file_config(file_cfg: PathSearcherConfigFile) -> eyre::Result<Self> {
         let languages: Arc<[String]> = file_cfg.languages.unwrap_or_else(default_languages).into();
         let prefixes: Arc<[String]> = file_cfg.prefixes.unwrap_or_else(default_prefixes).into();
@@ -81,6 +200,37 @@ impl PathSearcherConfig {
             .map(|s| canonicalize_platform_suffix(s.as_str()))
             .collect::<Vec<_>>()
             .into();
+        let mut prefix_platform_suffixes = FxHashMap::default();
+        for (prefix, suffixes) in file_cfg.prefix_platform_suffixes {
+            let Some(canonical) = prefixes
+                .iter()
+                .find(|p| p.eq_ignore_ascii_case(prefix.as_str()))
+            else {
+                eyre::bail!("prefix_platform_suffixes: {prefix:?} is not listed in prefixes");
+            };
+            let suffixes = suffixes
+                .iter()
+                .map(|s| canonicalize_platform_suffix(s.as_str()))
+                .collect();
+            prefix_platform_suffixes.insert(canonical.clone(), suffixes);
+        }
+        let platform_tags: Arc<[String]> =
+            canonicalize_tags(file_cfg.platform_tags.unwrap_or_else(default_platform_tags)).into();
+        let arch_tags: Arc<[String]> =
+            canonicalize_tags(file_cfg.arch_tags.unwrap_or_else(default_arch_tags)).into();
+        let candidate_templates: Arc<[CandidateTemplate]> = match file_cfg.candidate_templates {
+            Some(templates) => {
+                if templates.is_empty() {
+                    eyre::bail!("candidate_templates must not be empty");
+                }
+                templates
+                    .iter()
+                    .map(|t| CandidateTemplate::parse(t))
+                    .collect::<eyre::Result<Vec<_>>>()?
+                    .into()
+            }
+            None => default_candidate_templates().into(),
+        };
 
         let mut suffix_map = if file_cfg.use_builtin_suffix_map {
             default_suffix_map_full_owned()
@@ -98,6 +248,11 @@ impl PathSearcherConfig {
             languages,
             prefixes,
             platform_suffixes,
+            prefix_platform_suffixes: Arc::new(prefix_platform_suffixes),
+            platform_tags,
+            arch_tags,
+            candidate_templates,
+            match_all_versions: file_cfg.match_all_versions,
             suffix_map: Arc::new(suffix_map),
         })
     }
@@ -111,10 +266,29 @@ fn default_platform_suffixes() -> Vec<String> {
     vec!["X64".to_string(), "STM".to_string()]
 }
 
+fn default_platform_tags() -> Vec<String> {
+    vec!["STM".to_string(), "NSW".to_string(), "MSG".to_string()]
+}
+
+fn default_arch_tags() -> Vec<String> {
+    vec!["X64".to_string()]
+}
+
+fn canonicalize_tags(tags: Vec<String>) -> Vec<String> {
+    tags.into_iter()
+        .map(|s| s.trim().to_string())
+        .filter(|s| !s.is_empty())
+        .collect()
+}
+
 fn canonicalize_platform_suffix(s: &str) -> String {
     s.trim().to_uppercase()
 }
 
+fn default_candidate_templates() -> Vec<CandidateTemplate> {
+    vec![CandidateTemplate::parse(DEFAULT_CANDIDATE_TEMPLATE).expect("built-in template is valid")]
+}
+
 fn default_languages() -> Vec<String> {
     vec![
         "Ja", "En", "Fr", "It", "De", "Es", "Ru", "Pl", "Nl", "Pt", "PtBR", "Ko", "ZhTW", "ZhCN",
@@ -363,3 +537,37 @@ fn default_suffix_map_full() -> FxHashMap<&'static str, &'static [u32]> {
         ("zivacomb", &[240321828]),
     ])
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn toml_output_round_trips() {
+        let config = PathSearcherConfig::from_toml_str(
+            r#"
+prefixes = ["natives/STM/", "natives/NSW/"]
+match_all_versions = true
+
+[prefix_platform_suffixes]
+"natives/NSW/" = [".NSW"]
+"#,
+        )
+        .unwrap();
+
+        let reloaded =
+            PathSearcherConfig::from_toml_str(&config.to_toml_string().unwrap()).unwrap();
+        assert_eq!(reloaded.prefixes(), config.prefixes());
+        assert_eq!(reloaded.platform_suffixes(), config.platform_suffixes());
+        assert_eq!(
+            reloaded.platform_suffixes_for_prefix(Some("natives/NSW/")),
+            [".NSW"]
+        );
+        assert!(reloaded.match_all_versions());
+        assert_eq!(
+            reloaded.suffix_versions("tex"),
+            config.suffix_versions("tex")
+        );
+        assert_eq!(reloaded.suffix_map.len(), config.suffix_map.len());
+    }
+}
@@ -0,0 +1,196 @@
+use std::fmt;
+use std::str::FromStr;
+
+use color_eyre::eyre;
+
+/// The layout used when no `candidate_templates` are configured.
+///
+/// Matches the historical hardcoded order: prefix, raw path, version, then optional
+/// platform suffix and optional language, with `streaming/` probed for every hit.
+pub const DEFAULT_CANDIDATE_TEMPLATE: &str =
+    "{prefix}{streaming?}{raw}.{version}{.platform?}{.lang?}";
+
+/// A parsed candidate-generation template, e.g. `{prefix}{raw}.{version}{.lang?}`.
+///
+/// Placeholders are written as `{name}`, `{name?}` (optional) or `{.name?}` (optional, emitted
+/// with a leading dot). Supported names are `prefix`, `streaming`, `raw`, `version`, `platform`
+/// and `lang` (alias `language`). Everything outside braces is copied literally.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CandidateTemplate {
+    source: String,
+    segments: Vec<TemplateSegment>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TemplateSegment {
+    Literal(String),
+    Slot(TemplateSlot),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TemplateSlot {
+    pub kind: SlotKind,
+    pub leading_dot: bool,
+    pub optional: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SlotKind {
+    Prefix,
+    /// Only valid as `{streaming?}`: probed as `streaming/` once the same candidate without it exists.
+    Streaming,
+    Raw,
+    Version,
+    Platform,
+    Language,
+}
+
+impl CandidateTemplate {
+    pub fn parse(template: &str) -> eyre::Result<Self> {
+        let mut segments = vec![];
+        let mut literal = String::new();
+        let mut rest = template;
+
+        while let Some(open) = rest.find(['{', '}']) {
+            if rest.as_bytes()[open] == b'}' {
+                eyre::bail!("Unmatched '}}' in candidate template: {template}");
+            }
+            literal.push_str(&rest[..open]);
+            let close = rest[open..]
+                .find('}')
+                .map(|rel| open + rel)
+                .ok_or_else(|| eyre::eyre!("Unclosed '{{' in candidate template: {template}"))?;
+
+            if !literal.is_empty() {
+                segments.push(TemplateSegment::Literal(std::mem::take(&mut literal)));
+            }
+            segments.push(TemplateSegment::Slot(parse_slot(
+                &rest[open + 1..close],
+                template,
+            )?));
+            rest = &rest[close + 1..];
+        }
+        literal.push_str(rest);
+        if !literal.is_empty() {
+            segments.push(TemplateSegment::Literal(literal));
+        }
+
+        let count = |kind: SlotKind| {
+            segments
+                .iter()
+                .filter(|seg| matches!(seg, TemplateSegment::Slot(slot) if slot.kind == kind))
+                .count()
+        };
+        if count(SlotKind::Raw) != 1 {
+            eyre::bail!("Candidate template must contain exactly one {{raw}}: {template}");
+        }
+        if count(SlotKind::Version) != 1 {
+            eyre::bail!("Candidate template must contain exactly one {{version}}: {template}");
+        }
+        if count(SlotKind::Streaming) > 1 {
+            eyre::bail!("Candidate template may contain at most one {{streaming?}}: {template}");
+        }
+
+        Ok(Self {
+            source: template.to_string(),
+            segments,
+        })
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.source
+    }
+
+    pub fn segments(&self) -> &[TemplateSegment] {
+        &self.segments
+    }
+}
+
+impl FromStr for CandidateTemplate {
+    type Err = eyre::Report;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::parse(s)
+    }
+}
+
+impl fmt::Display for CandidateTemplate {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.source)
+    }
+}
+
+fn parse_slot(spec: &str, template: &str) -> eyre::Result<TemplateSlot> {
+    let (leading_dot, spec) = match spec.strip_prefix('.') {
+        Some(rest) => (true, rest),
+        None => (false, spec),
+    };
+    let (optional, name) = match spec.strip_suffix('?') {
+        Some(rest) => (true, rest),
+        None => (false, spec),
+    };
+
+    let kind = match name {
+        "prefix" => SlotKind::Prefix,
+        "streaming" => SlotKind::Streaming,
+        "raw" => SlotKind::Raw,
+        "version" => SlotKind::Version,
+        "platform" => SlotKind::Platform,
+        "lang" | "language" => SlotKind::Language,
+        _ => eyre::bail!("Unknown placeholder {{{spec}}} in candidate template: {template}"),
+    };
+
+    if kind == SlotKind::Streaming && (!optional || leading_dot) {
+        eyre::bail!("Streaming placeholder must be written as {{streaming?}}: {template}");
+    }
+    if optional && matches!(kind, SlotKind::Raw | SlotKind::Version) {
+        eyre::bail!("{{{name}}} cannot be optional: {template}");
+    }
+
+    Ok(TemplateSlot {
+        kind,
+        leading_dot,
+        optional,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_default_template() {
+        let t = CandidateTemplate::parse(DEFAULT_CANDIDATE_TEMPLATE).unwrap();
+        let slot = |kind, leading_dot, optional| {
+            TemplateSegment::Slot(TemplateSlot {
+                kind,
+                leading_dot,
+                optional,
+            })
+        };
+
+        assert_eq!(
+            t.segments(),
+            &[
+                slot(SlotKind::Prefix, false, false),
+                slot(SlotKind::Streaming, false, true),
+                slot(SlotKind::Raw, false, false),
+                TemplateSegment::Literal(".".to_string()),
+                slot(SlotKind::Version, false, false),
+                slot(SlotKind::Platform, true, true),
+                slot(SlotKind::Language, true, true),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_rejects_invalid_templates() {
+        assert!(CandidateTemplate::parse("{prefix}{raw}").is_err());
+        assert!(CandidateTemplate::parse("{raw}.{version}.{version}").is_err());
+        assert!(CandidateTemplate::parse("{raw}.{version}{.unknown?}").is_err());
+        assert!(CandidateTemplate::parse("{raw}.{version?}").is_err());
+        assert!(CandidateTemplate::parse("{streaming}{raw}.{version}").is_err());
+        assert!(CandidateTemplate::parse("{raw}.{version").is_err());
+        assert!(CandidateTemplate::parse("{raw}}.{version}").is_err());
+    }
+}
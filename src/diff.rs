@@ -0,0 +1,196 @@
+//! Comparison of two sets of resolved full paths, e.g. before and after a game update.
+
+use std::collections::BTreeMap;
+
+use crate::config::PathSearcherConfig;
+use crate::path_components::PathComponents;
+
+/// Paths added, removed or re-versioned between an old and a new path set.
+#[derive(Debug, Clone, Default)]
+pub struct PathDiff {
+    pub added: Vec<String>,
+    pub removed: Vec<String>,
+    /// `(old, new)` full paths of files whose version changed.
+    pub bumped: Vec<(String, String)>,
+    pub unchanged: usize,
+}
+
+/// Number of changes within one group of a [`PathDiff`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct DiffCounts {
+    pub added: usize,
+    pub removed: usize,
+    pub bumped: usize,
+}
+
+impl PathDiff {
+    /// Compare two sets of full paths. Paths are matched on everything but their version, so a
+    /// path whose version changed is reported as bumped instead of removed and added.
+    pub fn new<O, N>(old: O, new: N, config: &PathSearcherConfig) -> Self
+    where
+        O: IntoIterator,
+        O::Item: AsRef<str>,
+        N: IntoIterator,
+        N::Item: AsRef<str>,
+    {
+        // Versionless path -> (newest old path, newest new path).
+        let mut by_key: BTreeMap<String, (Option<Versioned>, Option<Versioned>)> = BTreeMap::new();
+        for path in old {
+            if let Some((key, path)) = versioned(path.as_ref(), config) {
+                keep_newest(&mut by_key.entry(key).or_default().0, path);
+            }
+        }
+        for path in new {
+            if let Some((key, path)) = versioned(path.as_ref(), config) {
+                keep_newest(&mut by_key.entry(key).or_default().1, path);
+            }
+        }
+
+        let mut diff = Self::default();
+        for (old, new) in by_key.into_values() {
+            match (old, new) {
+                (None, Some(new)) => diff.added.push(new.path),
+                (Some(old), None) => diff.removed.push(old.path),
+                (Some(old), Some(new)) if old.path != new.path => {
+                    diff.bumped.push((old.path, new.path))
+                }
+                (Some(_), Some(_)) => diff.unchanged += 1,
+                (None, None) => {}
+            }
+        }
+        diff
+    }
+
+    /// Change counts grouped by file extension.
+    pub fn by_extension(&self, config: &PathSearcherConfig) -> BTreeMap<String, DiffCounts> {
+        self.group_by(config, |parts| {
+            parts.extension().unwrap_or_default().to_string()
+        })
+    }
+
+    /// Change counts grouped by the first `depth` directories of the raw path.
+    pub fn by_directory(
+        &self,
+        config: &PathSearcherConfig,
+        depth: usize,
+    ) -> BTreeMap<String, DiffCounts> {
+        self.group_by(config, |parts| {
+            let raw = parts.raw_path();
+            let dir = raw.rfind('/').map_or("", |i| &raw[..i]);
+            dir.split('/').take(depth).collect::<Vec<_>>().join("/")
+        })
+    }
+
+    fn group_by(
+        &self,
+        config: &PathSearcherConfig,
+        key: impl Fn(&PathComponents) -> String,
+    ) -> BTreeMap<String, DiffCounts> {
+        let mut groups: BTreeMap<String, DiffCounts> = BTreeMap::new();
+        let group = |path: &str| {
+            let parts = PathComponents::parse(path, config)?;
+            Some(key(&parts))
+        };
+        for path in &self.added {
+            if let Some(k) = group(path) {
+                groups.entry(k).or_default().added += 1;
+            }
+        }
+        for path in &self.removed {
+            if let Some(k) = group(path) {
+                groups.entry(k).or_default().removed += 1;
+            }
+        }
+        for (_, path) in &self.bumped {
+            if let Some(k) = group(path) {
+                groups.entry(k).or_default().bumped += 1;
+            }
+        }
+        groups
+    }
+}
+
+struct Versioned {
+    path: String,
+    version: u64,
+}
+
+/// Split `path` into its versionless key and its version.
+fn versioned(path: &str, config: &PathSearcherConfig) -> Option<(String, Versioned)> {
+    let parts = PathComponents::parse(path, config)?;
+    let full = parts.normalized_full_path();
+    let (key, version) = match parts.version_range() {
+        // Drop the version together with the dot in front of it.
+        Some(range) => (
+            format!("{}{}", &full[..range.start - 1], &full[range.end..]),
+            full[range].parse().unwrap_or_default(),
+        ),
+        None => (full.to_string(), 0),
+    };
+    Some((
+        key.to_ascii_lowercase(),
+        Versioned {
+            path: parts.into_normalized_full_path(),
+            version,
+        },
+    ))
+}
+
+fn keep_newest(slot: &mut Option<Versioned>, candidate: Versioned) {
+    if slot.as_ref().is_none_or(|v| candidate.version > v.version) {
+        *slot = Some(candidate);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn diff_matches_paths_across_versions() {
+        let config = PathSearcherConfig::default();
+        let old = [
+            "natives/STM/gui/title.tex.100",
+            "natives/STM/gui/removed.tex.100",
+            "natives/STM/gui/text.msg.23.Ja",
+            "natives/STM/sound/same.bnk.2",
+        ];
+        let new = [
+            "natives/STM/gui/title.tex.200",
+            "natives/STM/gui/added.tex.200",
+            "natives/STM/gui/text.msg.23.Ja",
+            "natives/STM/gui/text.msg.23.En",
+            "natives/STM/sound/same.bnk.2",
+        ];
+
+        let diff = PathDiff::new(old, new, &config);
+        assert_eq!(
+            diff.added,
+            [
+                "natives/STM/gui/added.tex.200",
+                "natives/STM/gui/text.msg.23.En"
+            ]
+        );
+        assert_eq!(diff.removed, ["natives/STM/gui/removed.tex.100"]);
+        assert_eq!(
+            diff.bumped,
+            [(
+                "natives/STM/gui/title.tex.100".to_string(),
+                "natives/STM/gui/title.tex.200".to_string()
+            )]
+        );
+        assert_eq!(diff.unchanged, 2);
+
+        let by_ext = diff.by_extension(&config);
+        assert_eq!(
+            by_ext["tex"],
+            DiffCounts {
+                added: 1,
+                removed: 1,
+                bumped: 1
+            }
+        );
+        assert_eq!(by_ext["msg"].added, 1);
+        assert_eq!(diff.by_directory(&config, 1)["gui"].added, 2);
+    }
+}
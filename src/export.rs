@@ -0,0 +1,375 @@
+use std::fmt::Display;
+use std::fs::{self, File};
+use std::io::{BufWriter, Write};
+use std::path::Path;
+
+use color_eyre::eyre::{self, Context};
+use ree_pak_core::PakReader;
+use rustc_hash::FxHashSet;
+use serde::{Deserialize, Serialize};
+
+use crate::config::PathSearcherConfig;
+use crate::hash::{PathHasher, hash_path};
+use crate::pak::{EntryInfo, PakCollection};
+use crate::path_components::PathComponents;
+use crate::searcher::SearchResult;
+
+/// Output layout of search results.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ExportFormat {
+    /// `output.list`, `output_raw.list` and `unknown.list`, one path per line.
+    #[default]
+    Text,
+    /// `output.json`, a single array of path records.
+    Json,
+    /// `output.ndjson`, one path record per line.
+    Ndjson,
+    /// `output.csv`, one row per resolved full path.
+    Csv,
+}
+
+/// One scanned raw path with everything it resolved to.
+///
+/// Unknown paths are exported with an empty `resolved` list.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PathRecord {
+    pub raw_path: String,
+    pub resolved: Vec<ResolvedRecord>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ResolvedRecord {
+    pub full_path: String,
+    pub version: Option<String>,
+    pub platform: Option<String>,
+    pub arch: Option<String>,
+    pub language: Option<String>,
+    pub hash_lower: u32,
+    pub hash_upper: u32,
+    /// Where the file is stored, when exported together with the PAKs.
+    pub entry: Option<EntryInfo>,
+}
+
+impl ResolvedRecord {
+    pub fn new(full_path: &str, config: &PathSearcherConfig) -> Self {
+        let parts = PathComponents::parse(full_path, config);
+        let parts = parts.as_ref();
+
+        let mut hasher = PathHasher::new();
+        hasher.write_str(full_path);
+
+        Self {
+            full_path: full_path.to_string(),
+            version: parts.and_then(|p| p.version_str()).map(str::to_string),
+            platform: parts.and_then(|p| p.platform()).map(str::to_string),
+            arch: parts.and_then(|p| p.arch()).map(str::to_string),
+            language: parts.and_then(|p| p.language()).map(str::to_string),
+            hash_lower: hasher.finish_lower(),
+            hash_upper: hasher.finish_upper(),
+            entry: None,
+        }
+    }
+
+    /// Attach the metadata of the entry `pak` holds for this path.
+    pub fn with_entry<R: PakReader>(mut self, pak: Option<&PakCollection<R>>) -> Self {
+        self.entry = pak.and_then(|pak| pak.entry_info(hash_path(&self.full_path)));
+        self
+    }
+}
+
+#[derive(Serialize)]
+struct CsvRow<'a> {
+    raw_path: &'a str,
+    full_path: Option<&'a str>,
+    version: Option<&'a str>,
+    platform: Option<&'a str>,
+    arch: Option<&'a str>,
+    language: Option<&'a str>,
+    hash_lower: Option<u32>,
+    hash_upper: Option<u32>,
+    pak: Option<&'a str>,
+    offset: Option<u64>,
+    compressed_size: Option<u64>,
+    decompressed_size: Option<u64>,
+    compression: Option<&'a str>,
+}
+
+/// Build records for found paths, unknown paths, and extra full paths (e.g. from reference lists)
+/// that were not already reported. With `pak`, resolved paths carry their entry metadata.
+pub fn build_records<R: PakReader>(
+    result: &SearchResult,
+    extra_full_paths: &[String],
+    config: &PathSearcherConfig,
+    pak: Option<&PakCollection<R>>,
+) -> Vec<PathRecord> {
+    let mut records = Vec::with_capacity(result.found_paths.len() + result.unknown_paths.len());
+    let mut written = FxHashSet::default();
+
+    for (raw_path, infos) in &result.found_paths {
+        for info in infos {
+            written.insert(hash_path(&info.full_path));
+        }
+        records.push(PathRecord {
+            raw_path: raw_path.clone(),
+            resolved: infos
+                .iter()
+                .map(|info| ResolvedRecord::new(&info.full_path, config).with_entry(pak))
+                .collect(),
+        });
+    }
+
+    for path in extra_full_paths {
+        if !written.insert(hash_path(path)) {
+            continue;
+        }
+        let raw_path = PathComponents::parse(path, config)
+            .map(|parts| parts.raw_path().to_string())
+            .unwrap_or_else(|| path.clone());
+        records.push(PathRecord {
+            raw_path,
+            resolved: vec![ResolvedRecord::new(path, config).with_entry(pak)],
+        });
+    }
+
+    let mut unknown: Vec<&String> = result.unknown_paths.iter().collect();
+    unknown.sort_unstable();
+    records.extend(unknown.into_iter().map(|raw_path| PathRecord {
+        raw_path: raw_path.clone(),
+        resolved: vec![],
+    }));
+
+    records
+}
+
+/// Write results into `output_dir` using `format`. Structured formats include the entry metadata
+/// of resolved paths when `pak` is given.
+pub fn export_results<R: PakReader>(
+    output_dir: &Path,
+    format: ExportFormat,
+    result: &SearchResult,
+    extra_full_paths: &[String],
+    config: &PathSearcherConfig,
+    pak: Option<&PakCollection<R>>,
+) -> eyre::Result<()> {
+    fs::create_dir_all(output_dir)
+        .wrap_err_with(|| format!("Failed to create output directory: {output_dir:?}"))?;
+
+    match format {
+        ExportFormat::Text => export_text(output_dir, result, extra_full_paths),
+        ExportFormat::Json => {
+            let records = build_records(result, extra_full_paths, config, pak);
+            let mut writer = create_output(output_dir, "output.json")?;
+            serde_json::to_writer_pretty(&mut writer, &records)?;
+            writeln!(writer)?;
+            writer.flush()?;
+            Ok(())
+        }
+        ExportFormat::Ndjson => {
+            let records = build_records(result, extra_full_paths, config, pak);
+            let mut writer = create_output(output_dir, "output.ndjson")?;
+            for record in &records {
+                serde_json::to_writer(&mut writer, record)?;
+                writeln!(writer)?;
+            }
+            writer.flush()?;
+            Ok(())
+        }
+        ExportFormat::Csv => {
+            let records = build_records(result, extra_full_paths, config, pak);
+            let mut writer = csv::Writer::from_writer(create_output(output_dir, "output.csv")?);
+            for record in &records {
+                if record.resolved.is_empty() {
+                    writer.serialize(CsvRow {
+                        raw_path: &record.raw_path,
+                        full_path: None,
+                        version: None,
+                        platform: None,
+                        arch: None,
+                        language: None,
+                        hash_lower: None,
+                        hash_upper: None,
+                        pak: None,
+                        offset: None,
+                        compressed_size: None,
+                        decompressed_size: None,
+                        compression: None,
+                    })?;
+                }
+                for resolved in &record.resolved {
+                    let entry = resolved.entry.as_ref();
+                    writer.serialize(CsvRow {
+                        raw_path: &record.raw_path,
+                        full_path: Some(resolved.full_path.as_str()),
+                        version: resolved.version.as_deref(),
+                        platform: resolved.platform.as_deref(),
+                        arch: resolved.arch.as_deref(),
+                        language: resolved.language.as_deref(),
+                        hash_lower: Some(resolved.hash_lower),
+                        hash_upper: Some(resolved.hash_upper),
+                        pak: entry.map(|e| e.pak_name.as_str()),
+                        offset: entry.map(|e| e.offset),
+                        compressed_size: entry.map(|e| e.compressed_size),
+                        decompressed_size: entry.map(|e| e.decompressed_size),
+                        compression: entry.map(|e| e.compression.as_str()),
+                    })?;
+                }
+            }
+            writer.flush()?;
+            Ok(())
+        }
+    }
+}
+
+fn export_text(
+    output_dir: &Path,
+    result: &SearchResult,
+    extra_full_paths: &[String],
+) -> eyre::Result<()> {
+    let mut raw_writer = create_output(output_dir, "output_raw.list")?;
+    let mut writer = create_output(output_dir, "output.list")?;
+    let mut written = FxHashSet::default();
+
+    for (raw_path, indexes) in &result.found_paths {
+        for index in indexes {
+            writeln!(writer, "{}", index.full_path)?;
+            written.insert(hash_path(&index.full_path));
+        }
+        writeln!(raw_writer, "{}", raw_path)?;
+    }
+
+    for path in extra_full_paths {
+        if written.insert(hash_path(path)) {
+            writeln!(writer, "{path}")?;
+        }
+    }
+
+    let mut unknown_writer = create_output(output_dir, "unknown.list")?;
+    for path in &result.unknown_paths {
+        writeln!(unknown_writer, "{}", path)?;
+    }
+
+    raw_writer.flush()?;
+    writer.flush()?;
+    unknown_writer.flush()?;
+    Ok(())
+}
+
+/// Write `lines` into `output_dir/name`, one per line, creating the directory if needed.
+pub fn write_list<I>(output_dir: &Path, name: &str, lines: I) -> eyre::Result<()>
+where
+    I: IntoIterator,
+    I::Item: Display,
+{
+    fs::create_dir_all(output_dir)
+        .wrap_err_with(|| format!("Failed to create output directory: {output_dir:?}"))?;
+    let mut writer = create_output(output_dir, name)?;
+    for line in lines {
+        writeln!(writer, "{line}")?;
+    }
+    writer.flush()?;
+    Ok(())
+}
+
+fn create_output(output_dir: &Path, name: &str) -> eyre::Result<BufWriter<File>> {
+    let path = output_dir.join(name);
+    let file = File::create(&path).wrap_err_with(|| format!("Failed to create {path:?}"))?;
+    Ok(BufWriter::new(file))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{BufRead, BufReader, Cursor};
+
+    use super::*;
+    use crate::test_utils::pak;
+
+    #[test]
+    fn structured_exports_round_trip() {
+        let config = PathSearcherConfig::default();
+        let mut result = SearchResult::default();
+        result.unknown_paths.insert("test/unknown.user".to_string());
+        let full_paths = ["natives/STM/test/a.tex.241106027.STM".to_string()];
+        let no_pak: Option<&PakCollection<Cursor<Vec<u8>>>> = None;
+        let records = build_records(&result, &full_paths, &config, no_pak);
+        assert_eq!(records.len(), 2);
+
+        let dir = std::env::temp_dir().join(format!("ree-export-{}", std::process::id()));
+        let export = |format| export_results(&dir, format, &result, &full_paths, &config, no_pak);
+
+        export(ExportFormat::Json).unwrap();
+        let file = File::open(dir.join("output.json")).unwrap();
+        let parsed: Vec<PathRecord> = serde_json::from_reader(file).unwrap();
+        assert_eq!(parsed, records);
+
+        export(ExportFormat::Ndjson).unwrap();
+        let file = File::open(dir.join("output.ndjson")).unwrap();
+        let parsed: Vec<PathRecord> = BufReader::new(file)
+            .lines()
+            .map(|line| serde_json::from_str(&line.unwrap()).unwrap())
+            .collect();
+        assert_eq!(parsed, records);
+
+        export(ExportFormat::Csv).unwrap();
+        let mut reader = csv::Reader::from_path(dir.join("output.csv")).unwrap();
+        let rows: Vec<csv::StringRecord> = reader.records().map(Result::unwrap).collect();
+        assert_eq!(rows.len(), 2);
+        assert_eq!(&rows[0][1], full_paths[0]);
+        assert_eq!(&rows[0][2], "241106027");
+        assert_eq!(&rows[1][0], "test/unknown.user");
+        assert_eq!(&rows[1][1], "");
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn structured_exports_carry_entry_metadata() {
+        let config = PathSearcherConfig::default();
+        let full_path = "natives/STM/test/a.tex.241106027.STM";
+        let collection =
+            PakCollection::from_readers(vec![pak(&[(full_path, b"texture")])]).unwrap();
+        let info = collection.entry_info_for_path(full_path).unwrap();
+        let result = SearchResult::default();
+        let full_paths = [full_path.to_string()];
+        let records = build_records(&result, &full_paths, &config, Some(&collection));
+        assert_eq!(records[0].resolved[0].entry.as_ref(), Some(&info));
+
+        let dir = std::env::temp_dir().join(format!("ree-export-pak-{}", std::process::id()));
+        let export = |format| {
+            export_results(
+                &dir,
+                format,
+                &result,
+                &full_paths,
+                &config,
+                Some(&collection),
+            )
+        };
+
+        export(ExportFormat::Json).unwrap();
+        let file = File::open(dir.join("output.json")).unwrap();
+        let parsed: Vec<PathRecord> = serde_json::from_reader(file).unwrap();
+        assert_eq!(parsed, records);
+
+        export(ExportFormat::Ndjson).unwrap();
+        let line = fs::read_to_string(dir.join("output.ndjson")).unwrap();
+        let parsed: PathRecord = serde_json::from_str(line.trim_end()).unwrap();
+        assert_eq!(parsed, records[0]);
+
+        export(ExportFormat::Csv).unwrap();
+        let mut reader = csv::Reader::from_path(dir.join("output.csv")).unwrap();
+        let headers = reader.headers().unwrap().clone();
+        let rows: Vec<csv::StringRecord> = reader.records().map(Result::unwrap).collect();
+        assert_eq!(rows.len(), 1);
+        let column = |name: &str| {
+            let index = headers.iter().position(|header| header == name).unwrap();
+            rows[0][index].to_string()
+        };
+        assert_eq!(column("pak"), info.pak_name);
+        assert_eq!(column("offset"), info.offset.to_string());
+        assert_eq!(column("compressed_size"), info.compressed_size.to_string());
+        assert_eq!(column("decompressed_size"), "7");
+        assert_eq!(column("compression"), info.compression.as_str());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
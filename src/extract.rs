@@ -0,0 +1,296 @@
+//! Extraction of named PAK entries into a directory tree.
+//!
+//! Each file is written from the PAK the game loads it from, so patch and mod PAKs win over the
+//! ones they override, and lands at its full path below the output directory.
+
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use color_eyre::eyre::{self, Context};
+use parking_lot::Mutex;
+use ree_pak_core::PakReader;
+use rustc_hash::{FxHashMap, FxHashSet};
+
+use crate::config::PathSearcherConfig;
+use crate::hash;
+use crate::pak::{EntryInfo, PakCollection};
+use crate::path_components::PathComponents;
+
+/// Which full paths to extract. An empty filter matches everything.
+#[derive(Debug, Clone, Default)]
+pub struct ExtractFilter {
+    extensions: Vec<String>,
+    globs: Vec<String>,
+}
+
+impl ExtractFilter {
+    /// Only extract files with one of `extensions`, e.g. `tex` for `a.tex.241106027`.
+    pub fn with_extensions(
+        mut self,
+        extensions: impl IntoIterator<Item = impl Into<String>>,
+    ) -> Self {
+        self.extensions.extend(
+            extensions
+                .into_iter()
+                .map(|ext| ext.into().to_ascii_lowercase()),
+        );
+        self
+    }
+
+    /// Only extract files whose full path matches one of `globs`. `*` matches any run of
+    /// characters, `/` included, and `?` a single one; case is ignored.
+    pub fn with_globs(mut self, globs: impl IntoIterator<Item = impl Into<String>>) -> Self {
+        self.globs.extend(
+            globs
+                .into_iter()
+                .map(|glob| glob.into().to_ascii_lowercase()),
+        );
+        self
+    }
+
+    pub fn matches(&self, full_path: &str, config: &PathSearcherConfig) -> bool {
+        if !self.extensions.is_empty() {
+            let extension = PathComponents::parse(full_path, config)
+                .and_then(|parts| parts.extension().map(str::to_ascii_lowercase));
+            if !extension.is_some_and(|ext| self.extensions.contains(&ext)) {
+                return false;
+            }
+        }
+        if !self.globs.is_empty() {
+            let path = full_path.to_ascii_lowercase();
+            if !self
+                .globs
+                .iter()
+                .any(|glob| glob_match(glob.as_bytes(), path.as_bytes()))
+            {
+                return false;
+            }
+        }
+        true
+    }
+}
+
+/// A file to extract and the entry it comes from.
+#[derive(Debug, Clone)]
+pub struct PlannedFile {
+    pub full_path: String,
+    pub entry: EntryInfo,
+}
+
+/// Outcome of [`extract_files`].
+#[derive(Debug, Clone, Default)]
+pub struct ExtractSummary {
+    pub written: usize,
+    /// `(full path, error)` of every file that could not be extracted.
+    pub failed: Vec<(String, String)>,
+}
+
+/// The files of `full_paths` present in `collection` and matching `filter`, sorted by path. Each
+/// entry is planned once, from the PAK the game loads it from.
+pub fn plan_extraction<R, I>(
+    collection: &PakCollection<R>,
+    full_paths: I,
+    filter: &ExtractFilter,
+    config: &PathSearcherConfig,
+) -> Vec<PlannedFile>
+where
+    R: PakReader,
+    I: IntoIterator,
+    I::Item: AsRef<str>,
+{
+    let mut seen = FxHashSet::default();
+    let mut planned: Vec<PlannedFile> = full_paths
+        .into_iter()
+        .filter_map(|path| {
+            let path = path.as_ref();
+            if !filter.matches(path, config) {
+                return None;
+            }
+            let entry = collection.entry_info(hash::hash_path(path))?;
+            seen.insert(entry.hash).then(|| PlannedFile {
+                full_path: path.to_string(),
+                entry,
+            })
+        })
+        .collect();
+    planned.sort_by(|a, b| a.full_path.cmp(&b.full_path));
+    planned
+}
+
+/// Write the `planned` files below `output_dir`, extracting each PAK's entries in parallel.
+pub fn extract_files<R: PakReader>(
+    collection: &PakCollection<R>,
+    planned: &[PlannedFile],
+    output_dir: &Path,
+) -> eyre::Result<ExtractSummary> {
+    let mut summary = ExtractSummary::default();
+    let written = AtomicUsize::new(0);
+    // `(full path, output path)` of the files to write from each PAK, by entry hash.
+    let mut by_pak: FxHashMap<usize, FxHashMap<u64, (&str, PathBuf)>> = FxHashMap::default();
+    for file in planned {
+        match output_path(output_dir, &file.full_path) {
+            Some(path) => {
+                by_pak
+                    .entry(file.entry.pak_index)
+                    .or_default()
+                    .insert(file.entry.hash, (file.full_path.as_str(), path));
+            }
+            None => summary
+                .failed
+                .push((file.full_path.clone(), "unsafe path".to_string())),
+        }
+    }
+
+    let failed = Mutex::new(summary.failed);
+    let mut pak_indices: Vec<usize> = by_pak.keys().copied().collect();
+    pak_indices.sort_unstable();
+    for pak_index in pak_indices {
+        let targets = Arc::new(by_pak.remove(&pak_index).unwrap_or_default());
+        // Entries the callback saw, whether or not writing them succeeded.
+        let delivered = Mutex::new(FxHashSet::default());
+        let write =
+            |(full_path, path): &(&str, PathBuf), bytes: &[u8]| match write_file(path, bytes) {
+                Ok(()) => {
+                    written.fetch_add(1, Ordering::Relaxed);
+                }
+                Err(err) => failed
+                    .lock()
+                    .push((full_path.to_string(), format!("{err:#}"))),
+            };
+
+        collection.pak_files()[pak_index]
+            .extractor_callback()
+            .parallel(true)
+            .continue_on_error(true)
+            .filter({
+                let targets = Arc::clone(&targets);
+                move |entry, _path| targets.contains_key(&entry.hash())
+            })
+            .run_with_bytes(|entry, _rel_path, bytes| {
+                let hash = entry.hash();
+                let Some(target) = targets.get(&hash) else {
+                    return Ok(());
+                };
+                delivered.lock().insert(hash);
+                write(target, &bytes);
+                Ok(())
+            })?;
+
+        // Entries the extractor skipped are extracted again one by one for their error.
+        let delivered = delivered.into_inner();
+        for (&hash, target) in targets.iter() {
+            if delivered.contains(&hash) {
+                continue;
+            }
+            match collection.extract_entry(pak_index, hash) {
+                Ok(bytes) => write(target, &bytes),
+                Err(err) => failed
+                    .lock()
+                    .push((target.0.to_string(), format!("{err:#}"))),
+            }
+        }
+    }
+
+    summary.written = written.into_inner();
+    summary.failed = failed.into_inner();
+    summary.failed.sort();
+    Ok(summary)
+}
+
+/// `output_dir/full_path`, or `None` when the path would leave `output_dir`.
+fn output_path(output_dir: &Path, full_path: &str) -> Option<PathBuf> {
+    let relative = Path::new(full_path);
+    relative
+        .components()
+        .all(|component| matches!(component, Component::Normal(_)))
+        .then(|| output_dir.join(relative))
+}
+
+fn write_file(path: &Path, data: &[u8]) -> eyre::Result<()> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)
+            .wrap_err_with(|| format!("Failed to create directory: {parent:?}"))?;
+    }
+    fs::write(path, data).wrap_err_with(|| format!("Failed to write {path:?}"))
+}
+
+/// Match `text` against a glob of `*` and `?` wildcards.
+fn glob_match(glob: &[u8], text: &[u8]) -> bool {
+    // Position after the last `*`, and the text position it is currently matched up to.
+    let (mut g, mut t) = (0, 0);
+    let mut star: Option<(usize, usize)> = None;
+    while t < text.len() {
+        match glob.get(g) {
+            Some(b'*') => {
+                star = Some((g + 1, t));
+                g += 1;
+            }
+            Some(&c) if c == b'?' || c == text[t] => {
+                g += 1;
+                t += 1;
+            }
+            _ => match star {
+                Some((star_g, star_t)) => {
+                    g = star_g;
+                    t = star_t + 1;
+                    star = Some((star_g, star_t + 1));
+                }
+                None => return false,
+            },
+        }
+    }
+    glob[g..].iter().all(|&c| c == b'*')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::pak;
+
+    #[test]
+    fn globs_match_across_directories() {
+        assert!(glob_match(
+            b"natives/*/gui/*.tex.*",
+            b"natives/stm/gui/a/b.tex.1"
+        ));
+        assert!(glob_match(b"*.user.?", b"a/b.user.2"));
+        assert!(!glob_match(b"*.user.?", b"a/b.user.23"));
+        assert!(!glob_match(b"gui/*", b"natives/gui/a"));
+    }
+
+    #[test]
+    fn extracts_the_loaded_copy_of_matching_files() {
+        let (user, tex) = ("natives/STM/test/a.user.2", "natives/STM/test/b.tex.1");
+        let collection = PakCollection::from_readers(vec![
+            pak(&[(user, b"base"), (tex, b"texture")]),
+            pak(&[(user, b"patched")]),
+        ])
+        .unwrap();
+        let config = PathSearcherConfig::default();
+
+        let filter = ExtractFilter::default().with_extensions(["user"]);
+        let planned = plan_extraction(&collection, [user, tex, user], &filter, &config);
+        assert_eq!(planned.len(), 1);
+        assert_eq!(planned[0].entry.pak_index, 1);
+
+        let dir = std::env::temp_dir().join(format!("ree-extract-{}", std::process::id()));
+        let summary = extract_files(&collection, &planned, &dir).unwrap();
+        assert_eq!(summary.written, 1);
+        assert!(summary.failed.is_empty());
+        assert_eq!(fs::read(dir.join(user)).unwrap(), b"patched");
+        assert!(!dir.join(tex).exists());
+
+        // Failures name the full path, not where it was to be written.
+        let blocked = dir.join("blocked");
+        fs::write(&blocked, b"").unwrap();
+        let summary = extract_files(&collection, &planned, &blocked).unwrap();
+        assert_eq!(summary.written, 0);
+        let failed: Vec<&str> = summary.failed.iter().map(|(p, _)| p.as_str()).collect();
+        assert_eq!(failed, [user]);
+
+        assert!(output_path(&dir, "../escape.user.2").is_none());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,148 @@
+//! Discovery of the PAK files of a game install, in the order the engine loads them.
+//!
+//! The engine mounts `re_chunk_000.pak` first, followed by its sub PAKs
+//! (`re_chunk_000.pak.sub_000.pak`, ...). Each patch level `NNN` then adds
+//! `re_chunk_000.pak.patch_NNN.pak` and the matching `.sub_SSS.pak.patch_NNN.pak` files, lowest
+//! patch first. DLC PAKs under `dlc/` come last, sorted by name. A later PAK wins over earlier ones
+//! for the same entry, which is what [`crate::pak::PakCollection`] expects from its input order.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use color_eyre::eyre::{self, Context};
+
+/// Position of a chunk PAK in the load order.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+struct ChunkOrder {
+    patch: u32,
+    chunk: u32,
+    /// `0` for the main PAK, `1 + index` for sub PAKs.
+    sub: u32,
+}
+
+/// Find the PAK files of the game installed in `game_dir`, in load order.
+pub fn discover_pak_files(game_dir: &Path) -> eyre::Result<Vec<PathBuf>> {
+    let mut chunks: Vec<(ChunkOrder, PathBuf)> = vec![];
+    for entry in read_dir(game_dir)? {
+        if let Some(order) = entry
+            .file_name()
+            .and_then(|name| name.to_str())
+            .and_then(parse_chunk_name)
+        {
+            chunks.push((order, entry));
+        }
+    }
+    if chunks.is_empty() {
+        eyre::bail!("No re_chunk_*.pak files found in {game_dir:?}");
+    }
+    chunks.sort();
+
+    let mut paks: Vec<PathBuf> = chunks.into_iter().map(|(_, path)| path).collect();
+
+    let dlc_dir = game_dir.join("dlc");
+    if dlc_dir.is_dir() {
+        let mut dlc: Vec<PathBuf> = read_dir(&dlc_dir)?
+            .into_iter()
+            .filter(|path| {
+                path.extension()
+                    .is_some_and(|ext| ext.eq_ignore_ascii_case("pak"))
+            })
+            .collect();
+        dlc.sort();
+        paks.extend(dlc);
+    }
+
+    Ok(paks)
+}
+
+fn read_dir(dir: &Path) -> eyre::Result<Vec<PathBuf>> {
+    let mut files = vec![];
+    for entry in fs::read_dir(dir).wrap_err_with(|| format!("Failed to read directory: {dir:?}"))? {
+        let entry = entry?;
+        if entry.file_type()?.is_file() {
+            files.push(entry.path());
+        }
+    }
+    Ok(files)
+}
+
+/// Parse `re_chunk_CCC.pak[.sub_SSS.pak][.patch_NNN.pak]`, ignoring case.
+fn parse_chunk_name(name: &str) -> Option<ChunkOrder> {
+    let name = name.to_ascii_lowercase();
+    let rest = name.strip_prefix("re_chunk_")?;
+    let (chunk, mut rest) = split_number(rest, ".pak")?;
+
+    let mut sub = 0;
+    if let Some(tail) = rest.strip_prefix(".sub_") {
+        let (index, tail) = split_number(tail, ".pak")?;
+        sub = index + 1;
+        rest = tail;
+    }
+    let mut patch = 0;
+    if let Some(tail) = rest.strip_prefix(".patch_") {
+        let (level, tail) = split_number(tail, ".pak")?;
+        patch = level;
+        rest = tail;
+    }
+
+    rest.is_empty().then_some(ChunkOrder { patch, chunk, sub })
+}
+
+/// Split `"123<suffix>rest"` into `(123, "rest")`.
+fn split_number<'a>(s: &'a str, suffix: &str) -> Option<(u32, &'a str)> {
+    let digits = s.bytes().take_while(u8::is_ascii_digit).count();
+    if digits == 0 {
+        return None;
+    }
+    let number = s[..digits].parse().ok()?;
+    let rest = s[digits..].strip_prefix(suffix)?;
+    Some((number, rest))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn discovers_paks_in_load_order() {
+        let dir = std::env::temp_dir().join(format!("ree-game-dir-{}", std::process::id()));
+        fs::create_dir_all(dir.join("dlc")).unwrap();
+        for name in [
+            "re_chunk_000.pak.patch_002.pak",
+            "re_chunk_000.pak.sub_000.pak.patch_001.pak",
+            "RE_CHUNK_000.PAK",
+            "re_chunk_000.pak.patch_001.pak",
+            "re_chunk_000.pak.sub_000.pak",
+            "re_chunk_000.pak.patch_001.pak.bak",
+            "dlc/re_dlc_stm_2000001.pak",
+            "dlc/re_dlc_stm_1000001.pak",
+        ] {
+            fs::write(dir.join(name), b"").unwrap();
+        }
+
+        let paks = discover_pak_files(&dir).unwrap();
+        let names: Vec<String> = paks
+            .iter()
+            .map(|p| {
+                p.strip_prefix(&dir)
+                    .unwrap()
+                    .to_string_lossy()
+                    .replace('\\', "/")
+            })
+            .collect();
+        assert_eq!(
+            names,
+            [
+                "RE_CHUNK_000.PAK",
+                "re_chunk_000.pak.sub_000.pak",
+                "re_chunk_000.pak.patch_001.pak",
+                "re_chunk_000.pak.sub_000.pak.patch_001.pak",
+                "re_chunk_000.pak.patch_002.pak",
+                "dlc/re_dlc_stm_1000001.pak",
+                "dlc/re_dlc_stm_2000001.pak",
+            ]
+        );
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
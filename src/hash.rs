@@ -0,0 +1,197 @@
+//! Incremental form of the RE Engine path hash (`Utf16HashExt::hash_mixed`).
+//!
+//! The engine hashes the UTF-16LE encoding of the lower- and upper-cased path with murmur3
+//! (seed `0xFFFFFFFF`) and packs both halves as `upper << 32 | lower`. [`PathHasher`] keeps both
+//! running states, so a shared prefix can be hashed once and cheaply copied for every suffix.
+
+const SEED: u32 = 0xFFFF_FFFF;
+const C1: u32 = 0xcc9e_2d51;
+const C2: u32 = 0x1b87_3593;
+
+#[derive(Debug, Clone, Copy)]
+struct Murmur3State {
+    h1: u32,
+    /// Pending little-endian bytes of an incomplete 4-byte block.
+    tail: u32,
+    tail_len: u32,
+    len: u32,
+}
+
+impl Murmur3State {
+    const fn new() -> Self {
+        Self {
+            h1: SEED,
+            tail: 0,
+            tail_len: 0,
+            len: 0,
+        }
+    }
+
+    #[inline]
+    fn write_u16(&mut self, unit: u16) {
+        self.tail |= (unit as u32) << (self.tail_len * 8);
+        self.tail_len += 2;
+        self.len = self.len.wrapping_add(2);
+        if self.tail_len == 4 {
+            self.h1 ^= mix_k1(self.tail);
+            self.h1 = self.h1.rotate_left(13);
+            self.h1 = self.h1.wrapping_mul(5).wrapping_add(0xe654_6b64);
+            self.tail = 0;
+            self.tail_len = 0;
+        }
+    }
+
+    fn finish(&self) -> u32 {
+        let mut h1 = self.h1;
+        if self.tail_len != 0 {
+            h1 ^= mix_k1(self.tail);
+        }
+        h1 ^= self.len;
+        fmix32(h1)
+    }
+}
+
+#[inline]
+fn mix_k1(k1: u32) -> u32 {
+    k1.wrapping_mul(C1).rotate_left(15).wrapping_mul(C2)
+}
+
+#[inline]
+fn fmix32(mut h: u32) -> u32 {
+    h ^= h >> 16;
+    h = h.wrapping_mul(0x85eb_ca6b);
+    h ^= h >> 13;
+    h = h.wrapping_mul(0xc2b2_ae35);
+    h ^= h >> 16;
+    h
+}
+
+/// Running lower/upper case hash of a path, fed piece by piece.
+#[derive(Debug, Clone, Copy)]
+pub struct PathHasher {
+    lower: Murmur3State,
+    upper: Murmur3State,
+}
+
+impl Default for PathHasher {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl PathHasher {
+    pub const fn new() -> Self {
+        Self {
+            lower: Murmur3State::new(),
+            upper: Murmur3State::new(),
+        }
+    }
+
+    #[inline]
+    pub fn write_char(&mut self, c: char) {
+        if c.is_ascii() {
+            self.lower.write_u16(c.to_ascii_lowercase() as u16);
+            self.upper.write_u16(c.to_ascii_uppercase() as u16);
+            return;
+        }
+
+        let mut units = [0u16; 2];
+        for lc in c.to_lowercase() {
+            for &unit in lc.encode_utf16(&mut units).iter() {
+                self.lower.write_u16(unit);
+            }
+        }
+        for uc in c.to_uppercase() {
+            for &unit in uc.encode_utf16(&mut units).iter() {
+                self.upper.write_u16(unit);
+            }
+        }
+    }
+
+    #[inline]
+    pub fn write_str(&mut self, s: &str) {
+        for c in s.chars() {
+            self.write_char(c);
+        }
+    }
+
+    /// Lower-case half, as stored in PAK entries.
+    pub fn finish_lower(&self) -> u32 {
+        self.lower.finish()
+    }
+
+    /// Upper-case half, as stored in PAK entries.
+    pub fn finish_upper(&self) -> u32 {
+        self.upper.finish()
+    }
+
+    /// Combined hash, identical to `hash_mixed` of the written string.
+    pub fn finish(&self) -> u64 {
+        ((self.finish_upper() as u64) << 32) | self.finish_lower() as u64
+    }
+}
+
+/// Hash a complete path, identical to `Utf16HashExt::hash_mixed`.
+pub fn hash_path(path: &str) -> u64 {
+    let mut hasher = PathHasher::new();
+    hasher.write_str(path);
+    hasher.finish()
+}
+
+/// Parse a path hash as printed by tools: `0x`-prefixed hex, 16 hex digits, or decimal.
+pub fn parse_hash(s: &str) -> Option<u64> {
+    let s = s.trim();
+    if let Some(hex) = s.strip_prefix("0x").or_else(|| s.strip_prefix("0X")) {
+        return u64::from_str_radix(hex, 16).ok();
+    }
+    if s.len() == 16 && !s.bytes().all(|b| b.is_ascii_digit()) {
+        return u64::from_str_radix(s, 16).ok();
+    }
+    s.parse().ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use ree_pak_core::utf16_hash::Utf16HashExt;
+
+    use super::*;
+
+    #[test]
+    fn test_matches_hash_mixed() {
+        for path in [
+            "",
+            "a",
+            "ab",
+            "abc",
+            "natives/STM/systems/rendering/bluenoise256x256/hdr_rgba_0028.tex.241106027",
+            "natives/STM/GUI/Title.msg.23.Ja",
+        ] {
+            assert_eq!(hash_path(path), path.hash_mixed(), "{path}");
+        }
+    }
+
+    #[test]
+    fn test_incremental_matches_whole() {
+        let mut prefix = PathHasher::new();
+        prefix.write_str("natives/STM/");
+
+        let mut full = prefix;
+        full.write_str("test/sample.tex");
+        full.write_char('.');
+        full.write_str("241106027");
+
+        assert_eq!(
+            full.finish(),
+            hash_path("natives/STM/test/sample.tex.241106027")
+        );
+        assert_eq!(prefix.finish(), hash_path("natives/STM/"));
+    }
+
+    #[test]
+    fn test_parse_hash() {
+        assert_eq!(parse_hash("0x00000001FFFFFFFF"), Some(0x1_FFFF_FFFF));
+        assert_eq!(parse_hash("00000001fffffffF"), Some(0x1_FFFF_FFFF));
+        assert_eq!(parse_hash(" 8589934591 "), Some(0x1_FFFF_FFFF));
+        assert_eq!(parse_hash("natives/STM/a.tex"), None);
+    }
+}
@@ -1,10 +1,21 @@
 pub mod config;
+pub mod diff;
+pub mod export;
+pub mod extract;
+pub mod game_dir;
+pub mod hash;
+pub mod magic;
+pub mod overrides;
 pub mod path_components;
 mod searcher;
 
 pub mod pak;
+pub mod unnamed;
 pub mod utils;
 
+#[cfg(test)]
+mod test_utils;
+
 pub use config::PathSearcherConfig;
 pub use path_components::PathComponents;
 pub use searcher::*;
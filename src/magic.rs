@@ -0,0 +1,188 @@
+//! Recognition of RE Engine file formats from their leading bytes.
+//!
+//! Most formats open with a four byte magic, either at the start of the file or right after a
+//! leading version field. The table maps those to the extension the format is stored under, as
+//! named in the built-in suffix map. Formats left out of it are listed in the tests.
+
+/// A magic expected at `offset` in files with `extension`.
+struct Magic {
+    offset: usize,
+    bytes: &'static [u8; 4],
+    extension: &'static str,
+}
+
+const fn magic(offset: usize, bytes: &'static [u8; 4], extension: &'static str) -> Magic {
+    Magic {
+        offset,
+        bytes,
+        extension,
+    }
+}
+
+const MAGICS: &[Magic] = &[
+    magic(0, b"USR\0", "user"),
+    magic(0, b"PFB\0", "pfb"),
+    magic(0, b"SCN\0", "scn"),
+    magic(0, b"MDF\0", "mdf2"),
+    magic(0, b"MESH", "mesh"),
+    magic(0, b"TEX\0", "tex"),
+    magic(0, b"RCOL", "rcol"),
+    magic(0, b"EFXR", "efx"),
+    magic(0, b"BHVT", "bhvt"),
+    magic(0, b"CLIP", "clip"),
+    magic(0, b"CDEF", "cdef"),
+    magic(0, b"CFIL", "cfil"),
+    magic(0, b"BKHD", "sbnk"),
+    magic(0, b"AKPK", "spck"),
+    magic(0, b"RTEX", "rtex"),
+    magic(0, b"GPUC", "gpuc"),
+    magic(0, b"MMTR", "mmtr"),
+    magic(0, b"FOL\0", "fol"),
+    magic(0, b"UVAR", "uvar"),
+    magic(0, b"GPBF", "gpbf"),
+    magic(0, b"JCNS", "jcns"),
+    magic(0, b"SDFT", "sdftex"),
+    magic(0, b"CLT2", "cloth2"),
+    magic(4, b"mlst", "motlist"),
+    magic(4, b"mot ", "mot"),
+    magic(4, b"mbnk", "motbank"),
+    magic(4, b"GMSG", "msg"),
+    magic(4, b"GUIR", "gui"),
+    magic(4, b"CHIN", "chain"),
+    magic(4, b"mcam", "motcam"),
+    magic(4, b"mcbk", "mcambank"),
+    magic(4, b"mcml", "mcamlist"),
+    magic(4, b"mfs2", "motfsm2"),
+    magic(4, b"TML\0", "tml"),
+    magic(4, b"jmap", "jmap"),
+    magic(4, b"CHN2", "chain2"),
+    magic(4, b"ftyp", "mov"),
+];
+
+/// Bytes a header must span to be matched against every magic.
+pub const HEADER_LEN: usize = header_len();
+
+const fn header_len() -> usize {
+    let mut len = 0;
+    let mut i = 0;
+    while i < MAGICS.len() {
+        let end = MAGICS[i].offset + MAGICS[i].bytes.len();
+        if end > len {
+            len = end;
+        }
+        i += 1;
+    }
+    len
+}
+
+/// Extension of the format `header` starts with, if it is a known one.
+pub fn sniff_extension(header: &[u8]) -> Option<&'static str> {
+    MAGICS
+        .iter()
+        .find(|m| header.get(m.offset..m.offset + 4) == Some(m.bytes.as_slice()))
+        .map(|m| m.extension)
+}
+
+/// The table's spelling of `extension`, for extensions recorded by an earlier sniff.
+pub fn known_extension(extension: &str) -> Option<&'static str> {
+    MAGICS
+        .iter()
+        .find(|m| m.extension == extension)
+        .map(|m| m.extension)
+}
+
+/// How many paths files of a format tend to reference. PAK scans extract higher tiers first.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum YieldTier {
+    /// Object and resource descriptions that are full of paths.
+    High,
+    /// Formats of unknown yield, including entries whose format is not known yet.
+    Unknown,
+    /// Textures, meshes, audio and text, which hold few paths or are skipped entirely.
+    Low,
+}
+
+pub fn yield_tier(extension: &str) -> YieldTier {
+    match extension {
+        "user" | "pfb" | "scn" | "mdf2" | "motlist" | "motbank" | "motfsm2" | "fsmv2" | "bhvt"
+        | "efx" | "rcol" | "gui" | "cdef" | "cfil" | "chain" | "chain2" | "clip" | "tml"
+        | "jmap" | "fol" => YieldTier::High,
+        "tex" | "mesh" | "bnk" | "pck" | "sbnk" | "spck" | "msg" | "mov" | "wem" | "mot" => {
+            YieldTier::Low
+        }
+        _ => YieldTier::Unknown,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sniffs_magics_at_either_offset() {
+        assert_eq!(sniff_extension(b"USR\0\x01\0\0\0"), Some("user"));
+        assert_eq!(sniff_extension(b"\x55\0\0\0mlst\0\0"), Some("motlist"));
+        assert_eq!(sniff_extension(b"\x22\0\0\0GMSG"), Some("msg"));
+        assert_eq!(sniff_extension(b"USR"), None);
+        assert_eq!(sniff_extension(b"\0\0\0\0\0\0\0\0"), None);
+        assert_eq!(sniff_extension(b"\0\0\0\x18ftypmp42"), Some("mov"));
+        assert!(yield_tier("user") < yield_tier("unknown_ext"));
+        assert!(yield_tier("unknown_ext") < yield_tier("tex"));
+    }
+
+    #[test]
+    fn sniffed_extensions_are_in_the_suffix_map() {
+        let config = crate::config::PathSearcherConfig::default();
+        for m in MAGICS {
+            assert!(
+                config.suffix_versions(m.extension).is_some(),
+                "{} is not in the suffix map",
+                m.extension
+            );
+            assert_eq!(known_extension(m.extension), Some(m.extension));
+        }
+    }
+
+    #[test]
+    fn magics_cover_the_suffix_map_but_for_listed_formats() {
+        // Behavior trees written with the bhvt layout, so they sniff as bhvt.
+        let mut left_out = vec!["fsmv2"];
+        // Formats with no magic of their own, or none known here.
+        left_out.extend(
+            "
+            abcmesh aebs aecr aedt aeeq aefb aeir aelp aemc aemd aeme aeml aepp aerb aesr aetr
+            aimap aimapattr ainvm ainvmmgr aivspc aivspcmgr aiwayp aiwaypmgr amix apsrc arexprgraph
+            asrc auto capface caphand ccbk chainwnd chf clrp clsm clsp cmat coco csdf cset dblc def
+            dlg dlgcf dlglist dlgtml dlgtmllist ecob eem efcsv emesh exprgraph fbik fbxskel fgrl
+            filter finf fpolygon fslt fxct gcf gclo gcp gml gp gpumotlist gpus grnd gsty gtex gtl
+            guisd hapvib hf htex ies ift ik3dpath ikbodyrig ikdamage ikfs ikhd ikleg2 iklizard
+            iklookat iklookat2 ikls ikmulti ikspinecg iktrain iktrain2 ikwagon jntexprgraph
+            jointlodgroup jointsetting lfa lform lmap lod lprb maba mcol mmtrs motblend motface
+            mottree mpci nar ncf nmr ocioc oft ord particle path pci pfnn pog poglst prb prvs psop
+            rbs rbsl rcf rcfg rdc rdd refskel retarget retargetfleg retargetrig rfl rmat rmesh road
+            rtbs rtmr sbd scb scl scns sdf sfur skeleton slqg smt spmt sss sst star stl stmesh
+            strands sts svgn svgsq svx swexprgraph swgm swid swms tean terr tmlbld tmlfsm2 trtd
+            ucurve ucurvelist uvs vehicle vehicle2 vmap vsdf vsdflist vsrc vtxa wrap wsg ziva
+            zivacomb
+            "
+            .split_whitespace(),
+        );
+
+        let mut covered: Vec<&str> = MAGICS.iter().map(|m| m.extension).collect();
+        covered.sort_unstable();
+        let table_len = covered.len();
+        covered.dedup();
+        assert_eq!(covered.len(), table_len, "an extension has two magics");
+        let mut magics: Vec<_> = MAGICS.iter().map(|m| (m.offset, m.bytes)).collect();
+        magics.sort_unstable();
+        magics.dedup();
+        assert_eq!(magics.len(), table_len, "a magic names two extensions");
+
+        covered.extend(left_out);
+        covered.sort_unstable();
+        let config = crate::config::PathSearcherConfig::default();
+        let mut builtin: Vec<&str> = config.suffix_extensions().collect();
+        builtin.sort_unstable();
+        assert_eq!(covered, builtin);
+    }
+}
@@ -0,0 +1,156 @@
+//! What mod PAKs layered on top of the base game replace or add.
+//!
+//! Mods are loaded after the base PAKs, so for every entry they contain, the last mod holding it
+//! is the copy the game loads. An entry also present in a base PAK overrides it; otherwise the mod
+//! adds a new file. When several mods hold the same entry, all but the last are shadowed, which is
+//! reported as a conflict.
+
+use ree_pak_core::PakReader;
+use rustc_hash::FxHashMap;
+
+use crate::hash;
+use crate::pak::PakCollection;
+
+/// An entry contained in at least one mod PAK.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ModEntry {
+    pub hash: u64,
+    /// Full path of the entry, when one of the given paths hashes to it.
+    pub full_path: Option<String>,
+    /// Mod PAKs holding the entry, in load order. The last one wins.
+    pub mods: Vec<usize>,
+    /// Base PAKs holding the entry, in load order.
+    pub shadowed: Vec<usize>,
+}
+
+impl ModEntry {
+    /// The mod PAK whose copy the game loads.
+    pub fn winner(&self) -> usize {
+        *self.mods.last().expect("mod entries are held by a mod")
+    }
+
+    pub fn is_added(&self) -> bool {
+        self.shadowed.is_empty()
+    }
+
+    pub fn is_conflict(&self) -> bool {
+        self.mods.len() > 1
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct OverrideReport {
+    /// Named entries sorted by full path, then unnamed ones by hash.
+    pub entries: Vec<ModEntry>,
+}
+
+impl OverrideReport {
+    /// Analyze the PAKs of `collection` from index `first_mod` on as mods layered over the ones
+    /// before it. `full_paths` name the entries; entries none of them hash to stay unnamed.
+    pub fn new<R, I>(collection: &PakCollection<R>, first_mod: usize, full_paths: I) -> Self
+    where
+        R: PakReader,
+        I: IntoIterator,
+        I::Item: AsRef<str>,
+    {
+        let mut by_hash: FxHashMap<u64, ModEntry> = FxHashMap::default();
+        for (index, pak) in collection.pak_files().iter().enumerate().skip(first_mod) {
+            for entry in pak.metadata().entries().iter() {
+                let mods = &mut by_hash
+                    .entry(entry.hash())
+                    .or_insert_with(|| ModEntry {
+                        hash: entry.hash(),
+                        full_path: None,
+                        mods: vec![],
+                        shadowed: vec![],
+                    })
+                    .mods;
+                if mods.last() != Some(&index) {
+                    mods.push(index);
+                }
+            }
+        }
+        for (index, pak) in collection.pak_files().iter().enumerate().take(first_mod) {
+            for entry in pak.metadata().entries().iter() {
+                if let Some(mod_entry) = by_hash.get_mut(&entry.hash())
+                    && mod_entry.shadowed.last() != Some(&index)
+                {
+                    mod_entry.shadowed.push(index);
+                }
+            }
+        }
+        for path in full_paths {
+            let path = path.as_ref();
+            if let Some(mod_entry) = by_hash.get_mut(&hash::hash_path(path))
+                && mod_entry.full_path.is_none()
+            {
+                mod_entry.full_path = Some(path.to_string());
+            }
+        }
+
+        let mut entries: Vec<ModEntry> = by_hash.into_values().collect();
+        // `None` sorts first, so flip it to put unnamed entries last.
+        entries.sort_by(|a, b| {
+            (a.full_path.is_none(), &a.full_path, a.hash).cmp(&(
+                b.full_path.is_none(),
+                &b.full_path,
+                b.hash,
+            ))
+        });
+        Self { entries }
+    }
+
+    /// Entries replacing a file of the base game.
+    pub fn overridden(&self) -> impl Iterator<Item = &ModEntry> {
+        self.entries.iter().filter(|entry| !entry.is_added())
+    }
+
+    /// Entries the base game does not have.
+    pub fn added(&self) -> impl Iterator<Item = &ModEntry> {
+        self.entries.iter().filter(|entry| entry.is_added())
+    }
+
+    /// Entries held by more than one mod.
+    pub fn conflicts(&self) -> impl Iterator<Item = &ModEntry> {
+        self.entries.iter().filter(|entry| entry.is_conflict())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use super::*;
+
+    fn pak(paths: &[&str]) -> Cursor<Vec<u8>> {
+        let files: Vec<(&str, &[u8])> = paths.iter().map(|path| (*path, &b"test"[..])).collect();
+        crate::test_utils::pak(&files)
+    }
+
+    #[test]
+    fn reports_overrides_additions_and_conflicts() {
+        let (a, b, c) = (
+            "natives/STM/test/a.user.2",
+            "natives/STM/test/b.user.2",
+            "natives/STM/test/c.user.2",
+        );
+        let collection =
+            PakCollection::from_readers(vec![pak(&[a, b]), pak(&[a, c]), pak(&[a])]).unwrap();
+
+        let report = OverrideReport::new(&collection, 1, [a, b]);
+        assert_eq!(report.entries.len(), 2);
+
+        let overridden: Vec<&ModEntry> = report.overridden().collect();
+        assert_eq!(overridden.len(), 1);
+        assert_eq!(overridden[0].full_path.as_deref(), Some(a));
+        assert_eq!(overridden[0].shadowed, [0]);
+        assert_eq!(overridden[0].mods, [1, 2]);
+        assert_eq!(overridden[0].winner(), 2);
+        assert_eq!(report.conflicts().count(), 1);
+
+        let added: Vec<&ModEntry> = report.added().collect();
+        assert_eq!(added.len(), 1);
+        assert_eq!(added[0].full_path, None);
+        assert_eq!(added[0].hash, hash::hash_path(c));
+    }
+}
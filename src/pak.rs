@@ -1,14 +1,86 @@
-use std::{fs::File, io::Read, path::Path};
+use std::fs::File;
+use std::io::{Cursor, Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
 
 use color_eyre::eyre::{self, Context};
+use parking_lot::Mutex;
+use ree_pak_core::pak::{CompressionType, PakEntry};
 use ree_pak_core::{PakFile, PakReader, utf16_hash::Utf16HashExt};
 use rustc_hash::{FxHashMap, FxHashSet};
+use serde::{Deserialize, Serialize};
 
 /// Multiple PAK archive collection.
 pub struct PakCollection<R: PakReader> {
     entry_hashes: FxHashSet<u64>,
-    last_pak_for_hash: FxHashMap<u64, usize>,
+    /// `(pak index, entry index)` of the copy of each entry the game loads.
+    last_pak_for_hash: FxHashMap<u64, (usize, usize)>,
+    /// Earlier PAKs holding each hash stored in more than one PAK, in load order.
+    shadowed_paks: FxHashMap<u64, Vec<usize>>,
     pak_files: Vec<PakFile<R>>,
+    pak_names: Vec<String>,
+}
+
+/// Where the entry the game loads for a hash is stored.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct EntryInfo {
+    pub hash: u64,
+    /// Index into [`PakCollection::pak_files`].
+    pub pak_index: usize,
+    pub pak_name: String,
+    pub offset: u64,
+    pub compressed_size: u64,
+    pub decompressed_size: u64,
+    pub compression: Compression,
+}
+
+/// Compression method of a PAK entry, under the names exports use for it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Compression {
+    None,
+    Deflate,
+    Zstd,
+    /// A method this crate does not know the name of.
+    Unknown,
+}
+
+impl Compression {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::None => "none",
+            Self::Deflate => "deflate",
+            Self::Zstd => "zstd",
+            Self::Unknown => "unknown",
+        }
+    }
+
+    /// The method of an entry with `compression` flags. Flags of more than one method, or of one
+    /// this crate does not know, are [`Self::Unknown`].
+    pub fn from_type(compression: CompressionType) -> Self {
+        if compression.is_empty() {
+            Self::None
+        } else if compression == CompressionType::DEFLATE {
+            Self::Deflate
+        } else if compression == CompressionType::ZSTD {
+            Self::Zstd
+        } else {
+            Self::Unknown
+        }
+    }
+}
+
+/// Whether `entry` is kept in its PAK as is, neither compressed nor encrypted, so its bytes there
+/// can be read without extracting it.
+pub fn is_stored(entry: &PakEntry) -> bool {
+    entry.compression_type().is_empty() && entry.encryption_type().is_empty()
+}
+
+/// A PAK that could not be loaded.
+#[derive(Debug, Clone)]
+pub struct RejectedPak {
+    pub name: String,
+    pub error: String,
 }
 
 impl<R> PakCollection<R>
@@ -16,41 +88,206 @@ where
     R: PakReader,
 {
     pub fn from_readers(readers: Vec<R>) -> eyre::Result<Self> {
+        let readers = readers
+            .into_iter()
+            .enumerate()
+            .map(|(index, reader)| (format!("pak #{index}"), reader))
+            .collect();
+        let (collection, _) = Self::from_named_readers(readers, false)?;
+        Ok(collection)
+    }
+
+    /// Load `(name, reader)` pairs in load order. A PAK whose header cannot be read fails the
+    /// whole load, naming the PAK, unless `skip_bad` is set; then it is left out and returned
+    /// among the rejected PAKs.
+    pub fn from_named_readers(
+        readers: Vec<(String, R)>,
+        skip_bad: bool,
+    ) -> eyre::Result<(Self, Vec<RejectedPak>)> {
         let mut pak_files = Vec::with_capacity(readers.len());
+        let mut pak_names = Vec::with_capacity(readers.len());
+        let mut rejected = vec![];
         let mut entry_hashes = FxHashSet::default();
         let mut last_pak_for_hash = FxHashMap::default();
+        let mut shadowed_paks: FxHashMap<u64, Vec<usize>> = FxHashMap::default();
 
-        for (index, reader) in readers.into_iter().enumerate() {
-            let pak_file = PakFile::from_reader(reader)?;
-            for entry in pak_file.metadata().entries().iter() {
+        for (name, reader) in readers {
+            let pak_file = match PakFile::from_reader(reader) {
+                Ok(pak_file) => pak_file,
+                Err(err) if skip_bad => {
+                    rejected.push(RejectedPak {
+                        name,
+                        error: err.to_string(),
+                    });
+                    continue;
+                }
+                Err(err) => {
+                    return Err(err).wrap_err_with(|| format!("Failed to load pak file: {name}"));
+                }
+            };
+
+            let index = pak_files.len();
+            for (entry_index, entry) in pak_file.metadata().entries().iter().enumerate() {
                 let hash = entry.hash();
                 entry_hashes.insert(hash);
-                last_pak_for_hash.insert(hash, index);
+                if let Some((previous, _)) = last_pak_for_hash.insert(hash, (index, entry_index))
+                    && previous != index
+                {
+                    shadowed_paks.entry(hash).or_default().push(previous);
+                }
             }
 
             pak_files.push(pak_file);
+            pak_names.push(name);
         }
 
-        Ok(Self {
-            pak_files,
-            entry_hashes,
-            last_pak_for_hash,
-        })
+        Ok((
+            Self {
+                pak_files,
+                entry_hashes,
+                last_pak_for_hash,
+                shadowed_paks,
+                pak_names,
+            },
+            rejected,
+        ))
     }
 
     pub fn pak_files(&self) -> &[PakFile<R>] {
         &self.pak_files
     }
 
+    /// Names of the loaded PAKs, parallel to [`Self::pak_files`].
+    pub fn pak_names(&self) -> &[String] {
+        &self.pak_names
+    }
+
     pub fn unique_entry_count(&self) -> usize {
         self.entry_hashes.len()
     }
 
+    /// Order independent fingerprint of the entry hashes, stable across runs. Two collections
+    /// with the same entries resolve paths identically.
+    pub fn fingerprint(&self) -> u64 {
+        self.entry_hashes
+            .iter()
+            .fold(self.entry_hashes.len() as u64, |acc, &hash| {
+                acc.wrapping_add(splitmix64(hash))
+            })
+    }
+
     /// Return true if this `(pak_index, hash)` is the chosen “winner” for scanning.
     ///
     /// This matches the previous behavior where later PAKs overwrite earlier ones in the hash index.
     pub fn should_scan_hash_in_pak(&self, hash: u64, pak_index: usize) -> bool {
-        self.last_pak_for_hash.get(&hash).copied() == Some(pak_index)
+        self.last_pak_for_hash
+            .get(&hash)
+            .is_some_and(|&(index, _)| index == pak_index)
+    }
+}
+
+fn splitmix64(mut x: u64) -> u64 {
+    x = x.wrapping_add(0x9e37_79b9_7f4a_7c15);
+    x = (x ^ (x >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    x = (x ^ (x >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    x ^ (x >> 31)
+}
+
+/// A read-only memory map of a PAK file, cheap to clone across extraction threads.
+///
+/// Wrap it in a [`MmapPakReader`] to use it as a [`PakReader`]. Reads through the reader are
+/// plain copies out of the mapping, so parallel extraction needs no seeks or read syscalls, and
+/// pages are only loaded when an entry touching them is read. Entries stored without compression
+/// need no reader at all: [`Self::bytes_at`] borrows them straight from the mapping.
+#[derive(Debug, Clone)]
+pub struct MappedPak(Arc<memmap2::Mmap>);
+
+/// [`PakReader`] over a memory-mapped PAK file.
+pub type MmapPakReader = Cursor<MappedPak>;
+
+impl MappedPak {
+    pub fn open(path: impl AsRef<Path>) -> eyre::Result<Self> {
+        let path = path.as_ref();
+        let file =
+            File::open(path).wrap_err_with(|| format!("Failed to open pak file: {path:?}"))?;
+        // SAFETY: the mapping is read-only. Modifying or truncating a PAK while it is being scanned
+        // is undefined behavior, the same caveat every memory-mapped reader carries.
+        let mmap = unsafe { memmap2::Mmap::map(&file) }
+            .wrap_err_with(|| format!("Failed to map pak file: {path:?}"))?;
+        Ok(Self(Arc::new(mmap)))
+    }
+
+    pub fn reader(&self) -> MmapPakReader {
+        Cursor::new(self.clone())
+    }
+
+    /// The `len` bytes at `offset`, or `None` when they run past the end of the file.
+    pub fn bytes_at(&self, offset: u64, len: u64) -> Option<&[u8]> {
+        let start = usize::try_from(offset).ok()?;
+        let end = start.checked_add(usize::try_from(len).ok()?)?;
+        self.0.get(start..end)
+    }
+}
+
+/// Direct access to the bytes of a PAK file, for entries stored without compression, which can
+/// be scanned without going through the PAK reader.
+#[derive(Debug, Clone)]
+pub enum RawPak {
+    Mapped(MappedPak),
+    /// A PAK read through its file, opened again for each use.
+    File(PathBuf),
+}
+
+impl RawPak {
+    /// A reader of the `len` bytes at `offset`.
+    pub fn open_range(&self, offset: u64, len: u64) -> eyre::Result<Box<dyn Read + '_>> {
+        match self {
+            Self::Mapped(mapped) => {
+                let bytes = mapped
+                    .bytes_at(offset, len)
+                    .ok_or_else(|| eyre::eyre!("entry data runs past the end of the PAK"))?;
+                Ok(Box::new(bytes))
+            }
+            Self::File(path) => {
+                let mut file = File::open(path)
+                    .wrap_err_with(|| format!("Failed to open pak file: {path:?}"))?;
+                file.seek(SeekFrom::Start(offset))?;
+                Ok(Box::new(file.take(len)))
+            }
+        }
+    }
+
+    /// The bytes of each `(offset, len)` range, read through a single open of the PAK. Ranges
+    /// past the end of the PAK come back short, down to empty.
+    pub fn headers(&self, ranges: &[(u64, u64)]) -> eyre::Result<Vec<Vec<u8>>> {
+        match self {
+            Self::Mapped(mapped) => Ok(ranges
+                .iter()
+                .map(|&(offset, len)| {
+                    let start = (offset as usize).min(mapped.as_ref().len());
+                    let end = (start + len as usize).min(mapped.as_ref().len());
+                    mapped.as_ref()[start..end].to_vec()
+                })
+                .collect()),
+            Self::File(path) => {
+                let mut file = File::open(path)
+                    .wrap_err_with(|| format!("Failed to open pak file: {path:?}"))?;
+                let mut headers = Vec::with_capacity(ranges.len());
+                for &(offset, len) in ranges {
+                    let mut header = vec![];
+                    file.seek(SeekFrom::Start(offset))?;
+                    (&mut file).take(len).read_to_end(&mut header)?;
+                    headers.push(header);
+                }
+                Ok(headers)
+            }
+        }
+    }
+}
+
+impl AsRef<[u8]> for MappedPak {
+    fn as_ref(&self) -> &[u8] {
+        &self.0
     }
 }
 
@@ -77,4 +314,143 @@ where
         let hash = path.hash_mixed();
         self.entry_hashes.contains(&hash)
     }
+
+    pub fn contains_hash(&self, hash: u64) -> bool {
+        self.entry_hashes.contains(&hash)
+    }
+
+    /// Metadata of the entry the game loads for `hash`, i.e. the one in the last PAK holding it.
+    pub fn entry_info(&self, hash: u64) -> Option<EntryInfo> {
+        let &(pak_index, entry_index) = self.last_pak_for_hash.get(&hash)?;
+        let entry = self.pak_files[pak_index]
+            .metadata()
+            .entries()
+            .get(entry_index)?;
+        Some(EntryInfo {
+            hash,
+            pak_index,
+            pak_name: self.pak_names[pak_index].clone(),
+            offset: entry.offset(),
+            compressed_size: entry.compressed_size(),
+            decompressed_size: entry.decompressed_size(),
+            compression: Compression::from_type(entry.compression_type()),
+        })
+    }
+
+    pub fn entry_info_for_path(&self, path: &str) -> Option<EntryInfo> {
+        self.entry_info(path.hash_mixed())
+    }
+
+    /// Indices of every PAK holding an entry with `hash`, in load order. The last one is the copy
+    /// the game loads.
+    pub fn paks_for_hash(&self, hash: u64) -> Vec<usize> {
+        let Some(&(last, _)) = self.last_pak_for_hash.get(&hash) else {
+            return vec![];
+        };
+        let mut paks = self.shadowed_paks.get(&hash).cloned().unwrap_or_default();
+        paks.push(last);
+        paks
+    }
+
+    /// Extract the entry `hash` of the PAK `pak_index` on its own. Parallel extraction skips the
+    /// entries it fails on without saying why, so this is how their error is found out.
+    pub fn extract_entry(&self, pak_index: usize, hash: u64) -> eyre::Result<Vec<u8>> {
+        let data = Mutex::new(None);
+        self.pak_files[pak_index]
+            .extractor_callback()
+            .parallel(false)
+            .continue_on_error(false)
+            .filter(move |entry, _path| entry.hash() == hash)
+            .run_with_bytes(|_entry, _rel_path, bytes| {
+                *data.lock() = Some(bytes.to_vec());
+                Ok(())
+            })?;
+        data.into_inner().ok_or_else(|| {
+            eyre::eyre!(
+                "entry {hash:016x} not found in {}",
+                self.pak_names[pak_index]
+            )
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::{pak, pak_bytes_with};
+
+    #[test]
+    fn entry_info_describes_the_loaded_copy() {
+        let path = "natives/STM/test/a.user.2";
+        let collection = PakCollection::from_readers(vec![
+            pak(&[(path, b"base")]),
+            pak(&[("natives/STM/test/b.user.2", b"other"), (path, b"patched")]),
+        ])
+        .unwrap();
+
+        let info = collection.entry_info_for_path(path).unwrap();
+        assert_eq!(info.pak_index, 1);
+        assert_eq!(info.pak_name, "pak #1");
+        assert_eq!(info.decompressed_size, 7);
+        assert_eq!(info, collection.entry_info(info.hash).unwrap());
+        assert_eq!(collection.paks_for_hash(info.hash), [0, 1]);
+        assert!(
+            collection
+                .entry_info_for_path("natives/STM/test/c.user.2")
+                .is_none()
+        );
+    }
+
+    #[test]
+    fn entry_info_names_the_compression_written() {
+        let path = "natives/STM/test/a.user.2";
+        // Repetitive, so that every method actually shrinks it.
+        let data = b"compressible ".repeat(64);
+        for (compression, expected) in [
+            (CompressionType::NONE, Compression::None),
+            (CompressionType::DEFLATE, Compression::Deflate),
+            (CompressionType::ZSTD, Compression::Zstd),
+        ] {
+            let collection = PakCollection::from_readers(vec![Cursor::new(pak_bytes_with(
+                &[(path, &data)],
+                compression,
+            ))])
+            .unwrap();
+            let info = collection.entry_info_for_path(path).unwrap();
+            assert_eq!(info.compression, expected);
+            assert_eq!(info.decompressed_size, data.len() as u64);
+        }
+        assert_eq!(
+            serde_json::to_string(&Compression::Zstd).unwrap(),
+            "\"zstd\""
+        );
+    }
+
+    #[test]
+    fn raw_paks_read_byte_ranges() {
+        let dir = std::env::temp_dir().join(format!("ree-raw-pak-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("raw.pak");
+        std::fs::write(&path, b"0123456789").unwrap();
+
+        let read = |raw: RawPak, offset, len| {
+            let mut data = vec![];
+            raw.open_range(offset, len)?.read_to_end(&mut data)?;
+            eyre::Ok(data)
+        };
+        let mapped = RawPak::Mapped(MappedPak::open(&path).unwrap());
+        assert_eq!(read(mapped.clone(), 2, 3).unwrap(), b"234");
+        assert_eq!(
+            mapped.headers(&[(9, 4), (12, 1)]).unwrap(),
+            [b"9".to_vec(), vec![]]
+        );
+        assert!(read(mapped, 8, 3).is_err());
+        assert_eq!(read(RawPak::File(path.clone()), 7, 3).unwrap(), b"789");
+        assert_eq!(
+            RawPak::File(path).headers(&[(0, 2), (9, 4)]).unwrap(),
+            [b"01".to_vec(), b"9".to_vec()]
+        );
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
 }
@@ -192,10 +192,10 @@ impl<'a> PathComponents<'a> {
     pub fn set_platform_str(&mut self, new_platform: Option<&str>) -> bool {
         let canonical = match new_platform {
             None => None,
-            Some(s) if s.eq_ignore_ascii_case("STM") => Some("STM"),
-            Some(s) if s.eq_ignore_ascii_case("NSW") => Some("NSW"),
-            Some(s) if s.eq_ignore_ascii_case("MSG") => Some("MSG"),
-            Some(_) => return false,
+            Some(s) => match find_tag_ignore_ascii_case(self.config.platform_tags(), s) {
+                Some(tag) => Some(tag),
+                None => return false,
+            },
         };
         self.set_tag(TagKind::Platform, canonical)
     }
@@ -234,8 +234,10 @@ impl<'a> PathComponents<'a> {
     pub fn set_arch_str(&mut self, new_arch: Option<&str>) -> bool {
         let canonical = match new_arch {
             None => None,
-            Some(s) if s.eq_ignore_ascii_case("X64") => Some("X64"),
-            Some(_) => return false,
+            Some(s) => match find_tag_ignore_ascii_case(self.config.arch_tags(), s) {
+                Some(tag) => Some(tag),
+                None => return false,
+            },
         };
         self.set_tag(TagKind::Arch, canonical)
     }
@@ -346,7 +348,7 @@ fn is_digits(s: &str) -> bool {
 }
 
 fn is_tag(s: &str, config: &PathSearcherConfig) -> bool {
-    is_language_tag(config, s) || is_platform_tag(s) || is_arch_tag(s)
+    is_language_tag(config, s) || is_platform_tag(config, s) || is_arch_tag(config, s)
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -358,10 +360,10 @@ enum TagKind {
 }
 
 fn classify_tag(config: &PathSearcherConfig, s: &str) -> TagKind {
-    if is_platform_tag(s) {
+    if is_platform_tag(config, s) {
         return TagKind::Platform;
     }
-    if is_arch_tag(s) {
+    if is_arch_tag(config, s) {
         return TagKind::Arch;
     }
     if is_language_tag(config, s) {
@@ -374,26 +376,35 @@ fn parse_raw_path_range(
     mut normalized_full: String,
     config: &PathSearcherConfig,
 ) -> (String, Range<usize>) {
-    while normalized_full.starts_with('@') || normalized_full.starts_with('/') {
-        normalized_full.remove(0);
-    }
+    let (start, raw_path) = raw_path_bounds(&normalized_full, config);
+    normalized_full.drain(..start);
+    (normalized_full, raw_path)
+}
+
+/// Locate the raw path inside an already normalized (trimmed, forward-slash) path without copying.
+///
+/// Returns `(start, raw)`: `path[start..]` is what [`PathComponents::normalized_full_path`] would
+/// hold for `path`, and `raw` is the raw path range relative to `start`.
+pub(crate) fn raw_path_bounds(path: &str, config: &PathSearcherConfig) -> (usize, Range<usize>) {
+    let mut start = path.len() - path.trim_start_matches(['@', '/']).len();
 
     let mut raw_start = 0usize;
     for p in config.prefixes() {
-        if starts_with_ignore_ascii_case(normalized_full.as_str(), p.as_str()) {
+        if starts_with_ignore_ascii_case(&path[start..], p.as_str()) {
             raw_start = p.len();
             break;
         }
     }
     if raw_start == 0 {
         for p in config.prefixes() {
-            if let Some(pos) = find_ignore_ascii_case(normalized_full.as_str(), p.as_str()) {
-                normalized_full.drain(..pos);
+            if let Some(pos) = find_ignore_ascii_case(&path[start..], p.as_str()) {
+                start += pos;
                 raw_start = p.len();
                 break;
             }
         }
     }
+    let normalized_full = &path[start..];
 
     if let Some(rest) = strip_prefix_ignore_ascii_case(&normalized_full[raw_start..], "streaming/")
     {
@@ -401,20 +412,20 @@ fn parse_raw_path_range(
     }
 
     let mut raw_end = normalized_full.len();
-    if let Some((seg_a, dot_a)) = last_segment_range(&normalized_full, normalized_full.len()) {
+    if let Some((seg_a, dot_a)) = last_segment_range(normalized_full, normalized_full.len()) {
         let seg_a_str = &normalized_full[seg_a.clone()];
 
         if is_digits(seg_a_str) {
             // ... .<ext>.<version>
             raw_end = dot_a;
         } else if is_tag(seg_a_str, config) {
-            if let Some((seg_b, dot_b)) = last_segment_range(&normalized_full, dot_a) {
+            if let Some((seg_b, dot_b)) = last_segment_range(normalized_full, dot_a) {
                 let seg_b_str = &normalized_full[seg_b.clone()];
                 if is_digits(seg_b_str) {
                     // ... .<ext>.<version>.<tag>
                     raw_end = dot_b;
                 } else if is_tag(seg_b_str, config) {
-                    if let Some((seg_c, dot_c)) = last_segment_range(&normalized_full, dot_b) {
+                    if let Some((seg_c, dot_c)) = last_segment_range(normalized_full, dot_b) {
                         let seg_c_str = &normalized_full[seg_c.clone()];
                         if is_digits(seg_c_str) {
                             // ... .<ext>.<version>.<tag>.<tag>
@@ -430,15 +441,15 @@ fn parse_raw_path_range(
         raw_end = raw_start;
     }
 
-    (normalized_full, raw_start..raw_end)
+    (start, raw_start..raw_end)
 }
 
-pub fn is_platform_tag(s: &str) -> bool {
-    s.eq_ignore_ascii_case("STM") || s.eq_ignore_ascii_case("NSW") || s.eq_ignore_ascii_case("MSG")
+pub fn is_platform_tag(config: &PathSearcherConfig, s: &str) -> bool {
+    find_tag_ignore_ascii_case(config.platform_tags(), s).is_some()
 }
 
-pub fn is_arch_tag(s: &str) -> bool {
-    s.eq_ignore_ascii_case("X64")
+pub fn is_arch_tag(config: &PathSearcherConfig, s: &str) -> bool {
+    find_tag_ignore_ascii_case(config.arch_tags(), s).is_some()
 }
 
 pub fn is_language_tag(config: &PathSearcherConfig, s: &str) -> bool {
@@ -448,6 +459,12 @@ pub fn is_language_tag(config: &PathSearcherConfig, s: &str) -> bool {
         .any(|lang| lang.as_str().eq_ignore_ascii_case(s))
 }
 
+fn find_tag_ignore_ascii_case<'a>(tags: &'a [String], s: &str) -> Option<&'a str> {
+    tags.iter()
+        .find(|tag| tag.as_str().eq_ignore_ascii_case(s))
+        .map(|tag| tag.as_str())
+}
+
 fn starts_with_ignore_ascii_case(s: &str, prefix: &str) -> bool {
     s.get(..prefix.len())
         .is_some_and(|head| head.eq_ignore_ascii_case(prefix))
@@ -540,4 +557,30 @@ mod tests {
         assert_eq!(p.version_str(), None);
         assert_eq!(p.language(), None);
     }
+
+    #[test]
+    fn test_tags_follow_config() {
+        let cfg = PathSearcherConfig::from_toml_str(
+            r#"
+languages = ["En"]
+platform_tags = ["PS5"]
+arch_tags = ["A64"]
+"#,
+        )
+        .unwrap();
+
+        let mut p = PathComponents::parse("natives/STM/gui/title.msg.23.PS5.En", &cfg).unwrap();
+        assert_eq!(p.raw_path(), "gui/title.msg");
+        assert_eq!(p.platform(), Some("PS5"));
+        assert_eq!(p.language(), Some("En"));
+
+        assert!(!p.set_platform_str(Some("STM")));
+        assert!(p.set_platform_str(Some("ps5")));
+        assert_eq!(p.platform(), Some("PS5"));
+
+        let mut p = PathComponents::parse("natives/STM/gui/title.tex.1.A64", &cfg).unwrap();
+        assert_eq!(p.raw_path(), "gui/title.tex");
+        assert_eq!(p.arch(), Some("A64"));
+        assert!(!p.set_arch_str(Some("X64")));
+    }
 }
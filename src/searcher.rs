@@ -1,30 +1,44 @@
+mod cache;
 mod filter;
+mod scan_limits;
+mod scan_state;
 mod suffix;
 
 use std::borrow::Cow;
+use std::collections::BTreeMap;
 use std::fmt::Write as _;
 use std::fs::File;
-use std::path::Path;
+use std::hash::BuildHasher;
+use std::io::Read;
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
-use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::time::{Duration, Instant};
 
 use color_eyre::eyre::{self, Context};
-use dashmap::DashMap;
 use minidump::{Minidump, MinidumpMemory64List};
 use parking_lot::Mutex;
 use rayon::iter::{IntoParallelRefIterator, ParallelExtend, ParallelIterator};
 use ree_pak_core::{CloneableFile, PakReader};
-use rustc_hash::{FxBuildHasher, FxHashSet};
+use rustc_hash::{FxBuildHasher, FxHashMap, FxHashSet};
 use suffix::I18nPakFileInfo;
 
 use crate::config::PathSearcherConfig;
-use crate::pak::PakCollection;
-use crate::path_components::PathComponents;
+use crate::hash;
+use crate::magic::{self, YieldTier};
+use crate::pak::{self, MappedPak, MmapPakReader, PakCollection, RawPak, RejectedPak};
+use crate::path_components::{self, PathComponents};
+use crate::searcher::cache::{CacheKey, PathCache};
 use crate::searcher::filter::{DefaultFilter, FileContext, Filter};
-use crate::utils;
+use crate::searcher::scan_limits::{STREAM_CHUNK_SIZE, ScanLimits, ScanPass};
+use crate::searcher::scan_state::{EntryStamp, ScanState};
+use crate::unnamed::UnnamedEntries;
 
 pub trait ProgressCallback {
     fn on_progress(&self, current: u64, total: u64);
+
+    /// Called as PAK scans find paths, with the number of distinct raw paths found so far.
+    fn on_discovery(&self, _found: u64) {}
 }
 
 impl<F> ProgressCallback for F
@@ -42,32 +56,92 @@ pub struct SearchResult {
     /// (path, detailed infos)
     pub found_paths: Vec<(String, Vec<I18nPakFileInfo>)>,
     pub unknown_paths: FxHashSet<String>,
+    /// Resolved full paths that are not in the baseline, sorted.
+    pub new_paths: Vec<String>,
+    /// PAK entries that could not be extracted or scanned.
+    pub entry_errors: Vec<EntryError>,
+    /// Whether a PAK scan stopped at its path target or time budget, leaving entries unscanned.
+    pub stopped_early: bool,
+}
+
+impl SearchResult {
+    /// Full paths of every found path, in the order of [`Self::found_paths`].
+    pub fn full_paths(&self) -> impl Iterator<Item = &str> {
+        self.found_paths
+            .iter()
+            .flat_map(|(_, infos)| infos.iter().map(|info| info.full_path.as_str()))
+    }
+}
+
+/// A PAK entry left unscanned by an error.
+#[derive(Debug, Clone)]
+pub struct EntryError {
+    pub hash: u64,
+    /// Index into [`PakCollection::pak_files`].
+    pub pak_index: usize,
+    pub message: String,
 }
 
 pub struct PathSearcherBuilder<R> {
-    pak_source: Vec<R>,
+    /// `(name, reader)` pairs in load order.
+    pak_source: Vec<(String, R)>,
+    /// Direct access to the PAKs opened by path, by name.
+    raw_paks: FxHashMap<String, RawPak>,
+    /// PAKs that could not even be opened.
+    rejected_paks: Vec<RejectedPak>,
+    skip_bad_paks: bool,
     filter: Option<Arc<dyn Filter + Send + Sync>>,
     config: Arc<PathSearcherConfig>,
+    baseline: Vec<String>,
+    resolve_baseline_variants: bool,
+    cache_file: Option<PathBuf>,
+    scan_state_file: Option<PathBuf>,
+    scan_limits: ScanLimits,
 }
 
 impl<R: PakReader> Default for PathSearcherBuilder<R> {
     fn default() -> Self {
         Self {
             pak_source: vec![],
+            raw_paks: FxHashMap::default(),
+            rejected_paks: vec![],
+            skip_bad_paks: false,
             filter: Some(Arc::new(DefaultFilter)),
             config: Arc::new(PathSearcherConfig::default()),
+            baseline: vec![],
+            resolve_baseline_variants: false,
+            cache_file: None,
+            scan_state_file: None,
+            scan_limits: ScanLimits::default(),
         }
     }
 }
 
 impl<R: PakReader> PathSearcherBuilder<R> {
     pub fn with_pak_file(mut self, reader: R) -> eyre::Result<Self> {
-        self.pak_source.push(reader);
+        let name = format!("pak #{}", self.pak_source.len());
+        self.pak_source.push((name, reader));
         Ok(self)
     }
 
     pub fn with_pak_files(mut self, readers: impl IntoIterator<Item = R>) -> Self {
-        self.pak_source.extend(readers);
+        for reader in readers {
+            let name = format!("pak #{}", self.pak_source.len());
+            self.pak_source.push((name, reader));
+        }
+        self
+    }
+
+    /// Add a PAK under `name`, which is used in load errors and [`PakCollection::pak_names`].
+    pub fn with_named_pak_file(mut self, name: impl Into<String>, reader: R) -> Self {
+        self.pak_source.push((name.into(), reader));
+        self
+    }
+
+    /// Leave out PAKs that cannot be opened or parsed instead of failing the build. They are
+    /// listed by [`PathSearcher::rejected_paks`].
+    pub fn with_skip_bad_paks(mut self, skip: bool) -> Self {
+        self.skip_bad_paks = skip;
         self
     }
 
@@ -86,38 +160,233 @@ impl<R: PakReader> PathSearcherBuilder<R> {
         self
     }
 
+    /// Full paths that are already known. They are never resolved again and are left out of
+    /// [`SearchResult::new_paths`].
+    pub fn with_baseline(mut self, paths: impl IntoIterator<Item = impl Into<String>>) -> Self {
+        self.baseline.extend(paths.into_iter().map(Into::into));
+        self
+    }
+
+    /// Resolve the raw paths of the baseline like any other, so new language, platform or version
+    /// variants of known files are found too. Off by default, as it gives up the time saved by
+    /// taking the resolutions of baseline raw paths from the baseline itself.
+    pub fn with_resolve_baseline_variants(mut self, resolve: bool) -> Self {
+        self.resolve_baseline_variants = resolve;
+        self
+    }
+
+    /// Persist resolutions in `path` across runs. Entries are loaded at build time when the file
+    /// was written for the same PAK set and config, and written back by
+    /// [`PathSearcher::save_cache`].
+    pub fn with_cache_file(mut self, path: impl Into<PathBuf>) -> Self {
+        self.cache_file = Some(path.into());
+        self
+    }
+
+    /// Remember the strings found in each PAK entry in `path`, so later PAK scans only extract
+    /// entries that were added or changed since.
+    pub fn with_scan_state_file(mut self, path: impl Into<PathBuf>) -> Self {
+        self.scan_state_file = Some(path.into());
+        self
+    }
+
+    /// Cap the decompressed data a PAK scan holds at once, roughly. Entries too large to be
+    /// extracted alongside one per thread within `bytes` are extracted one at a time after the
+    /// others.
+    pub fn with_memory_budget(mut self, bytes: u64) -> Self {
+        self.scan_limits.memory_budget = Some(bytes);
+        self
+    }
+
+    /// Leave entries larger than `bytes` once decompressed out of PAK scans. They are reported
+    /// in [`SearchResult::entry_errors`] unless [`Self::with_stream_large_entries`] applies.
+    pub fn with_max_entry_size(mut self, bytes: u64) -> Self {
+        self.scan_limits.max_entry_size = Some(bytes);
+        self
+    }
+
+    /// Scan entries above the max entry size in chunks read straight from the PAK instead of
+    /// skipping them. This only works for entries stored without compression, in PAKs opened by
+    /// path with [`Self::with_pak_paths`] or [`Self::with_mapped_pak_paths`]; other readers
+    /// cannot be read past the extractor.
+    pub fn with_stream_large_entries(mut self, stream: bool) -> Self {
+        self.scan_limits.stream_large_entries = stream;
+        self
+    }
+
+    /// Stop PAK scans once `count` distinct raw paths were found, trading completeness for a quick
+    /// first result. Entries of high-yield formats are scanned first, across all PAKs. The format
+    /// is known from an earlier scan state, a baseline or cached full path, or, for entries stored
+    /// without compression in PAKs opened by path, from their header; other entries are scanned
+    /// after the high-yield ones in PAK order.
+    pub fn with_stop_after_paths(mut self, count: usize) -> Self {
+        self.scan_limits.stop_after_paths = Some(count);
+        self
+    }
+
+    /// Stop PAK scans that run longer than `budget`. Entries being extracted are still finished.
+    pub fn with_scan_time_budget(mut self, budget: Duration) -> Self {
+        self.scan_limits.time_budget = Some(budget);
+        self
+    }
+
     pub fn build(self) -> eyre::Result<PathSearcher<R>> {
+        let mut rejected_paks = self.rejected_paks;
+        if !self.skip_bad_paks
+            && let Some(rejected) = rejected_paks.first()
+        {
+            eyre::bail!(
+                "Failed to open pak file: {}: {}",
+                rejected.name,
+                rejected.error
+            );
+        }
         let pak_collection = if self.pak_source.is_empty() {
             None
         } else {
-            Some(Arc::new(PakCollection::from_readers(self.pak_source)?))
+            let (collection, rejected) =
+                PakCollection::from_named_readers(self.pak_source, self.skip_bad_paks)?;
+            rejected_paks.extend(rejected);
+            Some(Arc::new(collection))
+        };
+        let raw_paks: Arc<[Option<RawPak>]> = match &pak_collection {
+            Some(pak) => pak
+                .pak_names()
+                .iter()
+                .map(|name| self.raw_paks.get(name).cloned())
+                .collect(),
+            None => Arc::new([]),
+        };
+
+        // Baseline paths decide which full paths are new and hint at entry formats. The raw
+        // paths they are found under resolve to the baseline paths of the PAKs without a lookup,
+        // unless variants of them are to be found as well.
+        let mut baseline = FxHashMap::default();
+        let mut seeded: FxHashMap<String, Vec<I18nPakFileInfo>> = FxHashMap::default();
+        for path in &self.baseline {
+            let Some(parts) = PathComponents::parse(path, &self.config) else {
+                continue;
+            };
+            let tier = parts
+                .extension()
+                .map_or(YieldTier::Unknown, magic::yield_tier);
+            let full_hash = hash::hash_path(parts.normalized_full_path());
+            baseline.insert(full_hash, tier);
+            if !self.resolve_baseline_variants
+                && let Some(pak) = &pak_collection
+                && pak.contains_hash(full_hash)
+            {
+                let infos = seeded.entry(parts.raw_path().to_string()).or_default();
+                if !infos
+                    .iter()
+                    .any(|info| info.full_path == parts.normalized_full_path())
+                {
+                    infos.push(I18nPakFileInfo {
+                        full_path: parts.normalized_full_path().to_string(),
+                    });
+                }
+            }
+        }
+        let path_cache = PathCache::default();
+        let cache_file = match (self.cache_file, &pak_collection) {
+            (Some(path), Some(pak)) => {
+                let key = CacheKey {
+                    pak_fingerprint: pak.fingerprint(),
+                    config_fingerprint: self.config.fingerprint(),
+                };
+                cache::load(&path, key, &path_cache)?;
+                Some((Arc::from(path), key))
+            }
+            _ => None,
         };
+        // Full resolutions from the cache file are kept over the baseline's.
+        seeded.retain(|raw_path, _| !path_cache.contains_key(raw_path));
+        let mut seeded_raw_paths = FxHashSet::default();
+        for (raw_path, infos) in seeded {
+            path_cache.insert(raw_path.clone(), Some(infos));
+            seeded_raw_paths.insert(raw_path);
+        }
 
         Ok(PathSearcher {
             pak_collection,
-            path_cache: Arc::new(DashMap::default()),
+            path_cache: Arc::new(path_cache),
             filter: self.filter,
             config: self.config,
+            baseline: Arc::new(baseline),
+            seeded_raw_paths: Arc::new(seeded_raw_paths),
+            cache_file,
+            scan_state_file: self.scan_state_file.map(Arc::from),
+            rejected_paks: rejected_paks.into(),
+            scan_limits: self.scan_limits,
+            raw_paks,
         })
     }
 }
 
 impl PathSearcherBuilder<CloneableFile> {
+    /// Open PAK files by path. A file that cannot be opened fails [`Self::build`] with its path,
+    /// or is skipped with [`Self::with_skip_bad_paks`].
     pub fn with_pak_paths(self, paths: &[impl AsRef<Path>]) -> Self {
-        self.with_pak_files(
-            paths
-                .iter()
-                .map(|p| CloneableFile::new(File::open(p).unwrap()).unwrap())
-                .collect::<Vec<_>>(),
-        )
+        self.with_opened_paks(paths, |path| {
+            let reader = CloneableFile::new(File::open(path)?)?;
+            Ok((reader, Some(RawPak::File(path.to_path_buf()))))
+        })
+    }
+}
+
+impl PathSearcherBuilder<MmapPakReader> {
+    /// Memory-map PAK files by path, with the same error handling as
+    /// [`PathSearcherBuilder::with_pak_paths`]. PAK scans borrow the entries stored without
+    /// compression straight from the mappings instead of extracting them.
+    pub fn with_mapped_pak_paths(self, paths: &[impl AsRef<Path>]) -> Self {
+        self.with_opened_paks(paths, |path| {
+            let pak = MappedPak::open(path)?;
+            Ok((pak.reader(), Some(RawPak::Mapped(pak))))
+        })
+    }
+}
+
+impl<R: PakReader> PathSearcherBuilder<R> {
+    fn with_opened_paks(
+        mut self,
+        paths: &[impl AsRef<Path>],
+        open: impl Fn(&Path) -> eyre::Result<(R, Option<RawPak>)>,
+    ) -> Self {
+        for path in paths {
+            let path = path.as_ref();
+            let name = path.display().to_string();
+            match open(path) {
+                Ok((reader, raw)) => {
+                    if let Some(raw) = raw {
+                        self.raw_paks.insert(name.clone(), raw);
+                    }
+                    self.pak_source.push((name, reader));
+                }
+                Err(err) => self.rejected_paks.push(RejectedPak {
+                    name,
+                    error: format!("{err:#}"),
+                }),
+            }
+        }
+        self
     }
 }
 
 pub struct PathSearcher<R: PakReader> {
     pak_collection: Option<Arc<PakCollection<R>>>,
-    path_cache: Arc<DashMap<String, Option<Vec<I18nPakFileInfo>>, FxBuildHasher>>,
+    path_cache: Arc<PathCache>,
     filter: Option<Arc<dyn Filter + Send + Sync>>,
     config: Arc<PathSearcherConfig>,
+    /// Hashes of the baseline full paths, with the yield tier of their extension.
+    baseline: Arc<FxHashMap<u64, YieldTier>>,
+    /// Raw paths whose cached resolution was taken from the baseline rather than looked up.
+    seeded_raw_paths: Arc<FxHashSet<String>>,
+    cache_file: Option<(Arc<Path>, CacheKey)>,
+    scan_state_file: Option<Arc<Path>>,
+    rejected_paks: Arc<[RejectedPak]>,
+    scan_limits: ScanLimits,
+    /// Direct access to the PAKs opened by path, parallel to [`PakCollection::pak_files`].
+    raw_paks: Arc<[Option<RawPak>]>,
 }
 
 impl<R: PakReader> Clone for PathSearcher<R> {
@@ -127,6 +396,13 @@ impl<R: PakReader> Clone for PathSearcher<R> {
             path_cache: Arc::clone(&self.path_cache),
             filter: self.filter.clone(),
             config: Arc::clone(&self.config),
+            baseline: Arc::clone(&self.baseline),
+            seeded_raw_paths: Arc::clone(&self.seeded_raw_paths),
+            cache_file: self.cache_file.clone(),
+            scan_state_file: self.scan_state_file.clone(),
+            rejected_paks: Arc::clone(&self.rejected_paks),
+            scan_limits: self.scan_limits,
+            raw_paks: Arc::clone(&self.raw_paks),
         }
     }
 }
@@ -135,9 +411,16 @@ impl<R: PakReader> Default for PathSearcher<R> {
     fn default() -> Self {
         Self {
             pak_collection: None,
-            path_cache: Arc::new(DashMap::default()),
+            path_cache: Arc::new(PathCache::default()),
             filter: None,
             config: Arc::new(PathSearcherConfig::default()),
+            baseline: Arc::default(),
+            seeded_raw_paths: Arc::default(),
+            cache_file: None,
+            scan_state_file: None,
+            rejected_paks: Arc::new([]),
+            scan_limits: ScanLimits::default(),
+            raw_paks: Arc::new([]),
         }
     }
 }
@@ -162,6 +445,34 @@ impl<R: PakReader> PathSearcher<R> {
             .unwrap_or(0)
     }
 
+    /// PAKs left out of the collection because they could not be opened or parsed.
+    pub fn rejected_paks(&self) -> &[RejectedPak] {
+        &self.rejected_paks
+    }
+
+    /// Number of raw paths with a known resolution, including those loaded from the cache file
+    /// or taken from the baseline.
+    pub fn cached_path_count(&self) -> usize {
+        self.path_cache.len()
+    }
+
+    /// Write the resolution cache back to the file given to
+    /// [`PathSearcherBuilder::with_cache_file`]. Does nothing without one. Resolutions taken from
+    /// the baseline are left out, as they may miss variants.
+    pub fn save_cache(&self) -> eyre::Result<()> {
+        match &self.cache_file {
+            Some((path, key)) => cache::save(path, *key, &self.path_cache, |raw_path| {
+                self.seeded_raw_paths.contains(raw_path)
+            }),
+            None => Ok(()),
+        }
+    }
+
+    /// Whether `full_path` is part of the baseline given to the builder.
+    pub fn is_known(&self, full_path: &str) -> bool {
+        self.baseline.contains_key(&hash::hash_path(full_path))
+    }
+
     pub fn resolve_reference_line(&self, line: &str) -> eyre::Result<Vec<I18nPakFileInfo>> {
         let Some(pak) = &self.pak_collection else {
             return Ok(vec![]);
@@ -173,6 +484,9 @@ impl<R: PakReader> PathSearcher<R> {
 
         // Do not accept the reference entry as-is. If it carries a version/platform/language tail,
         // only reuse that tail while replacing the version from config.
+        // Every version present is collected here, as reference lists name specific files; only
+        // lines without a version go through `find_path_i18n`, where the newest version wins
+        // unless all versions are requested.
         if let (Some(version_range), Some(ext)) = (parts.version_range(), parts.extension())
             && let Some(versions) = self.config().suffix_versions(ext)
         {
@@ -214,6 +528,71 @@ impl<R: PakReader> PathSearcher<R> {
         suffix::find_path_i18n(pak, &self.config, &core_parts)
     }
 
+    /// The PAK entries none of `full_paths` hash to, classified by format. Formats recorded in the
+    /// scan state file are reused; the other entries are sniffed from their header where stored
+    /// without compression in a PAK opened by path, and extracted otherwise, except those above the
+    /// max entry size or [`crate::unnamed::DEFAULT_MAX_SNIFF_SIZE`].
+    pub fn unnamed_entries<I>(&self, full_paths: I) -> eyre::Result<UnnamedEntries>
+    where
+        I: IntoIterator,
+        I::Item: AsRef<str>,
+    {
+        let Some(pak) = &self.pak_collection else {
+            return Ok(UnnamedEntries::default());
+        };
+        let mut unnamed = UnnamedEntries::new(pak, full_paths);
+        if let Some(path) = &self.scan_state_file {
+            let state = ScanState::load(path)?;
+            let recorded: Vec<(u64, &'static str)> = unnamed
+                .unclassified()
+                .filter_map(|hash| Some((hash, magic::known_extension(state.format(hash)?)?)))
+                .collect();
+            for (hash, extension) in recorded {
+                unnamed.set_format(hash, extension);
+            }
+        }
+        unnamed.classify(pak, &self.raw_paks, self.scan_limits.max_entry_size)?;
+        Ok(unnamed)
+    }
+
+    /// Try `stems`, paths without an extension such as `natives/STM/GUI/ui010101`, as names of
+    /// unnamed entries.
+    ///
+    /// Each stem is only probed with the extensions unnamed entries were sniffed as, with the
+    /// versions, platforms and languages of that extension, and a hit only counts when it names
+    /// an unnamed entry of that extension or of unknown format. Returns the sorted full paths hit.
+    pub fn guess_unnamed(&self, stems: &[String], unnamed: &UnnamedEntries) -> Vec<String> {
+        let Some(pak) = &self.pak_collection else {
+            return vec![];
+        };
+        let extensions: Vec<&'static str> = unnamed.by_extension().into_keys().collect();
+
+        let mut found: Vec<String> = stems
+            .par_iter()
+            .flat_map_iter(|stem| {
+                let mut hits = vec![];
+                for extension in &extensions {
+                    let candidate = format!("{}.{extension}", stem.trim());
+                    let Some(parts) = PathComponents::parse(&candidate, &self.config) else {
+                        continue;
+                    };
+                    let infos = suffix::find_raw_path_i18n(pak, &self.config, parts.raw_path())
+                        .unwrap_or_default();
+                    hits.extend(
+                        infos
+                            .into_iter()
+                            .map(|info| info.full_path)
+                            .filter(|path| unnamed.accepts(hash::hash_path(path), extension)),
+                    );
+                }
+                hits
+            })
+            .collect();
+        found.sort_unstable();
+        found.dedup();
+        found
+    }
+
     pub fn with_filter(mut self, filter: Arc<dyn Filter + Send + Sync>) -> Self {
         self.filter = Some(filter);
         self
@@ -225,11 +604,16 @@ impl<R: PakReader> PathSearcher<R> {
     }
 
     fn should_skip_file(&self, data: &[u8], file_hash: Option<u64>) -> bool {
+        self.should_skip_header(data, data.len() as u64, file_hash)
+    }
+
+    /// [`Self::should_skip_file`] for a file of `file_size` bytes starting with `data`.
+    fn should_skip_header(&self, data: &[u8], file_size: u64, file_hash: Option<u64>) -> bool {
         if let Some(filter) = &self.filter {
             let context = FileContext {
-                file_size: data.len() as u64,
+                file_size,
                 file_hash,
-                data: data.to_vec(),
+                data: data[..data.len().min(filter::HEADER_LEN)].to_vec(),
             };
             filter.should_skip_file(&context).unwrap_or_default()
         } else {
@@ -318,8 +702,11 @@ where
         all_paths.dedup_by(|(p, _), (q, _)| p == q);
 
         Ok(SearchResult {
+            new_paths: self.new_paths(&all_paths),
             found_paths: all_paths,
             unknown_paths: unk_paths.into_inner(),
+            entry_errors: vec![],
+            stopped_early: false,
         })
     }
 
@@ -346,48 +733,331 @@ where
 
         let processed = Arc::new(AtomicU64::new(0));
 
-        for (pak_index, pak) in pak_collection.pak_files().iter().enumerate() {
-            let searcher = self.clone();
-            let all_paths = Arc::clone(&all_paths);
-            let unk_paths = Arc::clone(&unk_paths);
-            let processed = Arc::clone(&processed);
-
-            let allowed_hashes: FxHashSet<u64> = pak
-                .metadata()
-                .entries()
-                .iter()
-                .map(|entry| entry.hash())
-                .filter(|&hash| pak_collection.should_scan_hash_in_pak(hash, pak_index))
-                .collect();
+        // Entries unchanged since the last scan are resolved from their recorded strings.
+        let previous_state = match &self.scan_state_file {
+            Some(path) => Some(Arc::new(ScanState::load(path)?)),
+            None => None,
+        };
+        let next_state = Mutex::new(ScanState::default());
+        let entry_errors = Mutex::new(vec![]);
+
+        // Found raw paths, counted once each, and whether the scan stopped before the end.
+        let found_raw_paths = Mutex::new(FxHashSet::default());
+        let stopped = Arc::new(AtomicBool::new(false));
+        let deadline = self
+            .scan_limits
+            .time_budget
+            .map(|budget| Instant::now() + budget);
+        let collect_paths = |paths: Vec<(String, Vec<I18nPakFileInfo>)>| {
+            if paths.is_empty() {
+                return;
+            }
+            let found = {
+                let mut found_raw_paths = found_raw_paths.lock();
+                found_raw_paths.extend(paths.iter().map(|(raw, _)| FxBuildHasher.hash_one(raw)));
+                found_raw_paths.len()
+            };
+            all_paths.lock().extend(paths);
+            progress.on_discovery(found as u64);
+            if self
+                .scan_limits
+                .stop_after_paths
+                .is_some_and(|target| found >= target)
+            {
+                stopped.store(true, Ordering::Relaxed);
+            }
+        };
 
-            let seen_hashes = Mutex::new(FxHashSet::default());
+        // Entries are scheduled by the yield of their format, as last sniffed or as told by the
+        // extension of a full path already resolved to them.
+        let known_tiers = self.known_yield_tiers();
+        let tier_of = |hash: u64| {
+            previous_state
+                .as_ref()
+                .and_then(|previous| previous.format(hash))
+                .map(magic::yield_tier)
+                .or_else(|| known_tiers.get(&hash).copied())
+                .unwrap_or(YieldTier::Unknown)
+        };
 
-            pak.extractor_callback()
-                .parallel(true)
-                .continue_on_error(true)
-                .filter(move |entry, _path| {
-                    let hash = entry.hash();
-                    if !allowed_hashes.contains(&hash) {
-                        return false;
-                    }
-                    // Avoid scanning the same hash multiple times within the same PAK.
-                    seen_hashes.lock().insert(hash)
-                })
-                .run_with_bytes(|entry, _rel_path, bytes| {
-                    let hash = entry.hash();
+        let should_stop = || {
+            if deadline.is_some_and(|deadline| Instant::now() >= deadline) {
+                stopped.store(true, Ordering::Relaxed);
+            }
+            stopped.load(Ordering::Relaxed)
+        };
+
+        let too_large = |hash: u64, pak_index: usize, stamp: EntryStamp| {
+            entry_errors.lock().push(EntryError {
+                hash,
+                pak_index,
+                message: format!(
+                    "entry of {} bytes exceeds the max entry size",
+                    stamp.decompressed_size
+                ),
+            });
+            let count = processed.fetch_add(1, Ordering::Relaxed) + 1;
+            progress.on_progress(count, total_files);
+        };
 
-                    if !searcher.should_skip_file(&bytes, Some(hash))
-                        && let Ok(paths) = searcher.search_memory(&bytes, unk_paths.as_ref())
-                        && !paths.is_empty()
+        // Sort the entries of all PAKs into the passes they are scanned in, so high-yield entries
+        // of a later PAK are not held up by the low-yield ones of an earlier PAK. Each hash is only
+        // scanned from the PAK the game loads it from, so it identifies the entry.
+        let threads = rayon::current_num_threads();
+        let mut stamps: FxHashMap<u64, (usize, EntryStamp)> = FxHashMap::default();
+        let mut reused: Vec<(u64, EntryStamp)> = vec![];
+        let mut passes: BTreeMap<(YieldTier, ScanPass), BTreeMap<usize, FxHashSet<u64>>> =
+            BTreeMap::new();
+        for (pak_index, pak) in pak_collection.pak_files().iter().enumerate() {
+            // By file name, so the state survives moving the game directory.
+            let pak_name = Path::new(&pak_collection.pak_names()[pak_index]);
+            let pak_id =
+                hash::hash_path(&pak_name.file_name().unwrap_or_default().to_string_lossy());
+            let raw = self.raw_paks.get(pak_index).and_then(Option::as_ref);
+
+            let mut scheduled: Vec<(u64, EntryStamp, ScanPass, YieldTier)> = vec![];
+            for entry in pak.metadata().entries().iter() {
+                let hash = entry.hash();
+                // Avoid scanning the same hash multiple times within the same PAK.
+                if !pak_collection.should_scan_hash_in_pak(hash, pak_index)
+                    || stamps.contains_key(&hash)
+                {
+                    continue;
+                }
+                let stamp = EntryStamp {
+                    pak: pak_id,
+                    offset: entry.offset(),
+                    compressed_size: entry.compressed_size(),
+                    decompressed_size: entry.decompressed_size(),
+                    stored: pak::is_stored(entry),
+                };
+                stamps.insert(hash, (pak_index, stamp));
+
+                if let Some(previous) = &previous_state
+                    && previous.get(hash, stamp).is_some()
+                {
+                    reused.push((hash, stamp));
+                    continue;
+                }
+                let pass = match self.scan_limits.pass_for(stamp, threads) {
+                    // Stored entries of a mapped PAK need no extraction, so no memory.
+                    ScanPass::Parallel | ScanPass::Sequential
+                        if stamp.stored && matches!(raw, Some(RawPak::Mapped(_))) =>
                     {
-                        all_paths.lock().extend(paths);
+                        ScanPass::Mapped
                     }
+                    // Only PAKs opened by path can be read past their reader.
+                    ScanPass::Streamed if raw.is_none() => {
+                        too_large(hash, pak_index, stamp);
+                        continue;
+                    }
+                    ScanPass::TooLarge => {
+                        too_large(hash, pak_index, stamp);
+                        continue;
+                    }
+                    pass => pass,
+                };
+                scheduled.push((hash, stamp, pass, tier_of(hash)));
+            }
 
-                    let count = processed.fetch_add(1, Ordering::Relaxed) + 1;
-                    progress.on_progress(count, total_files);
+            // The header of a stored entry takes a single read from the PAK, so entries of
+            // unknown format are sniffed before they are scheduled. Compressed or encrypted
+            // entries would have to be extracted for that; they keep their tier until a scan
+            // state records it.
+            if let Some(raw) = raw {
+                let mut to_sniff: Vec<&mut (u64, EntryStamp, ScanPass, YieldTier)> = scheduled
+                    .iter_mut()
+                    .filter(|(_, stamp, _, tier)| *tier == YieldTier::Unknown && stamp.stored)
+                    .collect();
+                to_sniff.sort_unstable_by_key(|(_, stamp, _, _)| stamp.offset);
+                let ranges: Vec<(u64, u64)> = to_sniff
+                    .iter()
+                    .map(|(_, stamp, _, _)| {
+                        let len = stamp.decompressed_size.min(magic::HEADER_LEN as u64);
+                        (stamp.offset, len)
+                    })
+                    .collect();
+                // Entries that cannot be read fail with their error when they are scanned.
+                let headers = raw.headers(&ranges).unwrap_or_default();
+                for ((_, _, _, tier), header) in to_sniff.into_iter().zip(&headers) {
+                    if let Some(extension) = magic::sniff_extension(header) {
+                        *tier = magic::yield_tier(extension);
+                    }
+                }
+            }
+
+            for (hash, _, pass, tier) in scheduled {
+                passes
+                    .entry((tier, pass))
+                    .or_default()
+                    .entry(pak_index)
+                    .or_default()
+                    .insert(hash);
+            }
+        }
+
+        // Recorded strings are resolved first, as they need no extraction at all.
+        if let Some(previous) = &previous_state {
+            reused.par_iter().for_each(|&(hash, stamp)| {
+                let strings = previous.get(hash, stamp).unwrap_or_default();
+                let mut paths = vec![];
+                for path in strings {
+                    self.resolve_scanned_path(path, &mut paths, &unk_paths);
+                }
+                collect_paths(paths);
+                next_state
+                    .lock()
+                    .insert(hash, stamp, previous.format(hash), strings.to_vec());
+
+                let count = processed.fetch_add(1, Ordering::Relaxed) + 1;
+                progress.on_progress(count, total_files);
+            });
+        }
+
+        let finish_entry = |hash: u64,
+                            format: Option<&str>,
+                            result: eyre::Result<Vec<(String, Vec<I18nPakFileInfo>)>>,
+                            recorded: Vec<String>| {
+            let (pak_index, stamp) = stamps[&hash];
+            match result {
+                Ok(paths) => {
+                    collect_paths(paths);
+                    if previous_state.is_some() {
+                        next_state.lock().insert(hash, stamp, format, recorded);
+                    }
+                }
+                // Failed entries are left out of the state, so the next scan retries them.
+                Err(err) => entry_errors.lock().push(EntryError {
+                    hash,
+                    pak_index,
+                    message: format!("{err:#}"),
+                }),
+            }
+
+            let count = processed.fetch_add(1, Ordering::Relaxed) + 1;
+            progress.on_progress(count, total_files);
+        };
+        let scan_bytes = |hash: u64, bytes: &[u8]| {
+            let mut recorded = vec![];
+            let record = previous_state.is_some().then_some(&mut recorded);
+            let result = if self.should_skip_file(bytes, Some(hash)) {
+                Ok(vec![])
+            } else {
+                self.search_memory_recording(bytes, unk_paths.as_ref(), record)
+            };
+            finish_entry(hash, magic::sniff_extension(bytes), result, recorded);
+        };
+
+        for ((_, pass_kind), by_pak) in passes {
+            for (pak_index, pass) in by_pak {
+                if should_stop() {
+                    break;
+                }
+                let raw = self.raw_paks.get(pak_index).and_then(Option::as_ref);
+                match (pass_kind, raw) {
+                    (ScanPass::Mapped, Some(RawPak::Mapped(mapped))) => {
+                        pass.par_iter().for_each(|&hash| {
+                            if should_stop() {
+                                return;
+                            }
+                            let (_, stamp) = stamps[&hash];
+                            match mapped.bytes_at(stamp.offset, stamp.decompressed_size) {
+                                Some(bytes) => scan_bytes(hash, bytes),
+                                None => finish_entry(
+                                    hash,
+                                    None,
+                                    Err(eyre::eyre!("entry data runs past the end of the PAK")),
+                                    vec![],
+                                ),
+                            }
+                        });
+                    }
+                    (ScanPass::Streamed, Some(raw)) => {
+                        for hash in pass {
+                            if should_stop() {
+                                break;
+                            }
+                            let (_, stamp) = stamps[&hash];
+                            let mut recorded = vec![];
+                            let record = previous_state.is_some().then_some(&mut recorded);
+                            let mut format = None;
+                            let result = raw
+                                .open_range(stamp.offset, stamp.decompressed_size)
+                                .and_then(|reader| {
+                                    self.search_stored_entry(
+                                        reader,
+                                        hash,
+                                        stamp.decompressed_size,
+                                        &mut format,
+                                        unk_paths.as_ref(),
+                                        record,
+                                    )
+                                });
+                            finish_entry(hash, format, result, recorded);
+                        }
+                    }
+                    _ => {
+                        let pass = Arc::new(pass);
+                        let delivered = Mutex::new(FxHashSet::default());
+                        // Entries left out because the scan stopped, which are not failures.
+                        let skipped: Arc<Mutex<FxHashSet<u64>>> = Arc::default();
+
+                        pak_collection.pak_files()[pak_index]
+                            .extractor_callback()
+                            .parallel(pass_kind == ScanPass::Parallel)
+                            .continue_on_error(true)
+                            .filter({
+                                let pass = Arc::clone(&pass);
+                                let skipped = Arc::clone(&skipped);
+                                let stopped = Arc::clone(&stopped);
+                                let seen_hashes = Mutex::new(FxHashSet::default());
+                                move |entry, _path| {
+                                    let hash = entry.hash();
+                                    if !pass.contains(&hash) || !seen_hashes.lock().insert(hash) {
+                                        return false;
+                                    }
+                                    if deadline.is_some_and(|deadline| Instant::now() >= deadline) {
+                                        stopped.store(true, Ordering::Relaxed);
+                                    }
+                                    if stopped.load(Ordering::Relaxed) {
+                                        skipped.lock().insert(hash);
+                                        return false;
+                                    }
+                                    true
+                                }
+                            })
+                            .run_with_bytes(|entry, _rel_path, bytes| {
+                                delivered.lock().insert(entry.hash());
+                                // The filter may have let the entry through before the stop.
+                                if !stopped.load(Ordering::Relaxed) {
+                                    scan_bytes(entry.hash(), &bytes);
+                                }
+                                Ok(())
+                            })?;
+
+                        // The extractor continues past entries it fails on, so the entries it
+                        // never delivered failed. Extracting them again one by one tells why.
+                        let delivered = delivered.into_inner();
+                        let skipped = std::mem::take(&mut *skipped.lock());
+                        let mut failed: Vec<u64> = pass
+                            .iter()
+                            .copied()
+                            .filter(|hash| !delivered.contains(hash) && !skipped.contains(hash))
+                            .collect();
+                        failed.sort_unstable();
+                        for hash in failed {
+                            match pak_collection.extract_entry(pak_index, hash) {
+                                Ok(bytes) => scan_bytes(hash, &bytes),
+                                Err(err) => finish_entry(hash, None, Err(err), vec![]),
+                            }
+                        }
+                    }
+                }
+            }
+        }
 
-                    Ok(())
-                })?;
+        if let Some(path) = &self.scan_state_file {
+            next_state.into_inner().save(path)?;
         }
 
         let mut all_paths = Arc::try_unwrap(all_paths)
@@ -398,19 +1068,120 @@ where
         all_paths.dedup_by(|(p, _), (q, _)| p == q);
 
         Ok(SearchResult {
+            new_paths: self.new_paths(&all_paths),
             found_paths: all_paths,
             unknown_paths: Arc::try_unwrap(unk_paths)
                 .map_err(|_| eyre::eyre!("unknown_paths still shared"))?
                 .into_inner(),
+            entry_errors: entry_errors.into_inner(),
+            stopped_early: stopped.load(Ordering::Relaxed),
         })
     }
 
+    /// Yield tiers of the entries a full path is already known for, from the baseline and the
+    /// resolution cache.
+    fn known_yield_tiers(&self) -> FxHashMap<u64, YieldTier> {
+        let mut tiers = (*self.baseline).clone();
+        for cached in self.path_cache.iter() {
+            for info in cached.value().iter().flatten() {
+                if let Some(parts) = PathComponents::parse(&info.full_path, &self.config)
+                    && let Some(extension) = parts.extension()
+                {
+                    tiers.insert(
+                        hash::hash_path(parts.normalized_full_path()),
+                        magic::yield_tier(extension),
+                    );
+                }
+            }
+        }
+        tiers
+    }
+
+    fn new_paths(&self, found_paths: &[(String, Vec<I18nPakFileInfo>)]) -> Vec<String> {
+        let mut new_paths: Vec<String> = found_paths
+            .iter()
+            .flat_map(|(_, infos)| infos.iter())
+            .filter(|info| !self.is_known(&info.full_path))
+            .map(|info| info.full_path.clone())
+            .collect();
+        new_paths.sort_unstable();
+        new_paths.dedup();
+        new_paths
+    }
+
+    /// Scan a stored entry of `size` bytes by reading it from `reader` in chunks, so it is never
+    /// held in memory whole. Each chunk is cut where no string can cross and the rest carried
+    /// into the next one. The format sniffed from the first chunk is written to `format`.
+    fn search_stored_entry(
+        &self,
+        reader: impl Read,
+        hash: u64,
+        size: u64,
+        format: &mut Option<&'static str>,
+        unk_paths: &Mutex<FxHashSet<String>>,
+        mut record: Option<&mut Vec<String>>,
+    ) -> eyre::Result<Vec<(String, Vec<I18nPakFileInfo>)>> {
+        let mut reader = reader.take(size);
+
+        let mut paths = vec![];
+        let mut window: Vec<u8> = Vec::with_capacity(STREAM_CHUNK_SIZE * 2);
+        let mut first = true;
+        loop {
+            let start = window.len();
+            window.resize(start + STREAM_CHUNK_SIZE, 0);
+            let mut filled = start;
+            while filled < window.len() {
+                match reader.read(&mut window[filled..])? {
+                    0 => break,
+                    n => filled += n,
+                }
+            }
+            window.truncate(filled);
+            let last = filled < start + STREAM_CHUNK_SIZE;
+
+            if first {
+                first = false;
+                *format = magic::sniff_extension(&window);
+                if self.should_skip_header(&window, size, Some(hash)) {
+                    return Ok(paths);
+                }
+            }
+
+            let cut = if last {
+                window.len()
+            } else {
+                scan_limits::safe_cut(&window)
+            };
+            paths.extend(self.search_memory_recording(
+                &window[..cut],
+                unk_paths,
+                record.as_deref_mut(),
+            )?);
+            window.drain(..cut);
+            if last {
+                return Ok(paths);
+            }
+        }
+    }
+
     fn search_memory(
         &self,
         memory: &[u8],
         unk_paths: &Mutex<FxHashSet<String>>,
+    ) -> eyre::Result<Vec<(String, Vec<I18nPakFileInfo>)>> {
+        self.search_memory_recording(memory, unk_paths, None)
+    }
+
+    /// [`Self::search_memory`], also collecting every candidate string into `record` so it can
+    /// be resolved again later without rescanning.
+    fn search_memory_recording(
+        &self,
+        memory: &[u8],
+        unk_paths: &Mutex<FxHashSet<String>>,
+        mut record: Option<&mut Vec<String>>,
     ) -> eyre::Result<Vec<(String, Vec<I18nPakFileInfo>)>> {
         let mut paths = vec![];
+        let mut scratch = String::with_capacity(256);
         const SLASH_U16: [u8; 2] = [b'/', 0];
         let mut pos = 0;
 
@@ -455,60 +1226,81 @@ where
             }
             pos = (end + 2).min(memory.len());
 
-            let Some(path) = utils::string_from_utf16_bytes(&memory[begin..end]) else {
-                continue;
-            };
+            // Every accepted code unit is ASCII, so decoding is a plain byte copy into the
+            // scratch buffer. Strings are only allocated for hits and unknown paths.
+            scratch.clear();
+            scratch.extend(memory[begin..end].iter().step_by(2).map(|&b| b as char));
+            let path = scratch.as_str();
 
-            if !validate_path(&path) {
+            if !validate_path(path) {
                 continue;
             }
 
-            let Some(parts) = PathComponents::parse(&path, &self.config) else {
+            let path = path.trim();
+            if path.is_empty() || path.starts_with('#') {
                 continue;
-            };
-            let raw_path = parts.raw_path().to_string();
-
-            if let Some(pak) = &self.pak_collection {
-                // Check cache first
-                if let Some(cached_result) = self.path_cache.get(raw_path.as_str()) {
-                    // Cache hit
-                    if let Some(cached_result) = cached_result.value() {
-                        paths.push((raw_path, cached_result.clone()));
-                    } else {
-                        // If stores None, then ignore
-                    }
-                    continue;
-                }
+            }
+            if let Some(record) = record.as_deref_mut() {
+                record.push(path.to_string());
+            }
+            self.resolve_scanned_path(path, &mut paths, unk_paths);
+        }
 
-                // Fast path: already a full path that exists in PAKs.
-                if pak.contains_path(parts.normalized_full_path()) {
-                    let full = parts.clone().into_normalized_full_path();
-                    let infos = vec![I18nPakFileInfo { full_path: full }];
-                    self.path_cache
-                        .insert(raw_path.clone(), Some(infos.clone()));
-                    paths.push((raw_path, infos));
-                    continue;
-                }
+        Ok(paths)
+    }
 
-                // Perform lookup
-                let file_hashes =
-                    suffix::find_path_i18n(pak, &self.config, &parts).unwrap_or_default();
-                if file_hashes.is_empty() {
-                    unk_paths.lock().insert(raw_path.clone());
-                    self.path_cache.insert(raw_path, None);
-                    continue;
-                }
+    /// Resolve one trimmed candidate string found by [`Self::search_memory`].
+    fn resolve_scanned_path(
+        &self,
+        path: &str,
+        paths: &mut Vec<(String, Vec<I18nPakFileInfo>)>,
+        unk_paths: &Mutex<FxHashSet<String>>,
+    ) {
+        let (start, raw_range) = path_components::raw_path_bounds(path, &self.config);
+        let normalized_full = &path[start..];
+        let raw_path = &normalized_full[raw_range];
+
+        let Some(pak) = &self.pak_collection else {
+            paths.push((raw_path.to_string(), vec![]));
+            return;
+        };
 
-                // Cache the result
-                self.path_cache
-                    .insert(raw_path.clone(), Some(file_hashes.clone()));
-                paths.push((raw_path, file_hashes));
+        // Check cache first
+        if let Some(cached_result) = self.path_cache.get(raw_path) {
+            // Cache hit
+            if let Some(cached_result) = cached_result.value() {
+                paths.push((raw_path.to_string(), cached_result.clone()));
             } else {
-                paths.push((raw_path, vec![]));
+                // Known unresolvable, but still unknown to this search.
+                unk_paths.lock().insert(raw_path.to_string());
             }
+            return;
         }
 
-        Ok(paths)
+        // Fast path: already a full path that exists in PAKs.
+        if pak.contains_hash(hash::hash_path(normalized_full)) {
+            let infos = vec![I18nPakFileInfo {
+                full_path: normalized_full.to_string(),
+            }];
+            self.path_cache
+                .insert(raw_path.to_string(), Some(infos.clone()));
+            paths.push((raw_path.to_string(), infos));
+            return;
+        }
+
+        // Perform lookup
+        let file_hashes =
+            suffix::find_raw_path_i18n(pak, &self.config, raw_path).unwrap_or_default();
+        if file_hashes.is_empty() {
+            unk_paths.lock().insert(raw_path.to_string());
+            self.path_cache.insert(raw_path.to_string(), None);
+            return;
+        }
+
+        // Cache the result
+        self.path_cache
+            .insert(raw_path.to_string(), Some(file_hashes.clone()));
+        paths.push((raw_path.to_string(), file_hashes));
     }
 }
 
@@ -546,33 +1338,110 @@ fn validate_path(path: &str) -> bool {
 
 #[cfg(test)]
 mod tests {
-    use std::io::{Cursor, Write};
+    use std::io::Cursor;
 
-    use ree_pak_core::write::{FileOptions, PakWriter};
+    use ree_pak_core::pak::CompressionType;
 
     use super::*;
+    use crate::pak::Compression;
+    use crate::test_utils::{pak, pak_bytes, pak_bytes_with};
 
     fn build_searcher_with_paths(
         config: PathSearcherConfig,
         paths: &[&str],
     ) -> eyre::Result<PathSearcher<Cursor<Vec<u8>>>> {
-        let mut pak_bytes = Vec::new();
-        let buffer = Cursor::new(&mut pak_bytes);
-        let mut writer = PakWriter::new(buffer, paths.len() as u64);
-        for path in paths {
-            writer.start_file(*path, FileOptions::default())?;
-            writer.write_all(b"test")?;
-        }
-        writer.finish()?;
+        build_searcher_with_baseline(config, paths, &[])
+    }
 
+    fn build_searcher_with_baseline(
+        config: PathSearcherConfig,
+        paths: &[&str],
+        baseline: &[&str],
+    ) -> eyre::Result<PathSearcher<Cursor<Vec<u8>>>> {
+        let files: Vec<(&str, &[u8])> = paths.iter().map(|path| (*path, &b"test"[..])).collect();
         PathSearcher::<Cursor<Vec<u8>>>::builder()
             .with_config(config)
-            .with_pak_file(Cursor::new(pak_bytes))?
+            .with_pak_file(pak(&files))?
+            .with_baseline(baseline.iter().copied())
             .build()
     }
 
+    fn utf16_blob(strings: &[&str]) -> Vec<u8> {
+        let mut data = b"TESTDATA".to_vec();
+        for s in strings {
+            data.extend(s.encode_utf16().flat_map(u16::to_le_bytes));
+            data.extend([0, 0]);
+        }
+        data
+    }
+
     #[test]
-    fn resolve_reference_line_replaces_only_version_and_keeps_tail_tags() {
+    fn bad_paks_fail_by_name_or_are_skipped() {
+        let pak_bytes = pak_bytes(&[("natives/STM/test/a.tex.1", b"test")]);
+        let builder = |skip| {
+            PathSearcher::<Cursor<Vec<u8>>>::builder()
+                .with_named_pak_file("good.pak", Cursor::new(pak_bytes.clone()))
+                .with_named_pak_file("bad.pak", Cursor::new(vec![0; 64]))
+                .with_skip_bad_paks(skip)
+        };
+
+        let Err(err) = builder(false).build() else {
+            panic!("corrupt PAK must fail the build");
+        };
+        assert!(format!("{err:#}").contains("bad.pak"));
+
+        let searcher = builder(true).build().unwrap();
+        assert_eq!(searcher.pak_collection().unwrap().pak_names(), ["good.pak"]);
+        let rejected: Vec<&str> = searcher
+            .rejected_paks()
+            .iter()
+            .map(|r| r.name.as_str())
+            .collect();
+        assert_eq!(rejected, ["bad.pak"]);
+    }
+
+    #[test]
+    fn guesses_only_count_for_entries_of_the_sniffed_extension() {
+        let config = PathSearcherConfig::from_toml_str(
+            r#"
+languages = ["Ja"]
+prefixes = ["natives/STM/"]
+platform_suffixes = ["STM"]
+use_builtin_suffix_map = false
+
+[suffix_map]
+scn = [21]
+user = [3]
+"#,
+        )
+        .unwrap();
+        let (scene, named, mislabeled) = (
+            "natives/STM/test/a.scn.21.STM",
+            "natives/STM/test/a.user.3.STM",
+            "natives/STM/test/b.user.3.STM",
+        );
+
+        let searcher = PathSearcher::<Cursor<Vec<u8>>>::builder()
+            .with_config(config)
+            .with_pak_file(pak(&[
+                (scene, b"SCN\0\0\0\0\0"),
+                (named, b"USR\0\0\0\0\0"),
+                // A scene stored under a user path is not what a `.user` guess is looking for.
+                (mislabeled, b"SCN\0\0\0\0\0"),
+            ]))
+            .unwrap()
+            .build()
+            .unwrap();
+        let unnamed = searcher.unnamed_entries([named]).unwrap();
+        assert_eq!(unnamed.len(), 2);
+        assert_eq!(unnamed.format(hash::hash_path(mislabeled)), Some("scn"));
+
+        let stems = ["natives/STM/test/a".to_string(), "test/b".to_string()];
+        assert_eq!(searcher.guess_unnamed(&stems, &unnamed), [scene]);
+    }
+
+    #[test]
+    fn mapped_paks_are_scanned_like_in_memory_ones() {
         let config = PathSearcherConfig::from_toml_str(
             r#"
 languages = ["Ja"]
@@ -586,23 +1455,635 @@ tex = [241106027]
         )
         .unwrap();
 
-        let searcher = build_searcher_with_paths(
-            config,
+        let pak_bytes = pak_bytes_with(
             &[
-                "natives/STM/test/sample.tex.241106027.STM.Ja",
-                "natives/STM/test/sample.tex.999999999.STM.Ja",
+                ("natives/STM/test/a.tex.241106027.STM", b"test"),
+                (
+                    "natives/STM/test/source.user.2",
+                    &utf16_blob(&["test/a.tex"]),
+                ),
             ],
-        )
-        .unwrap();
+            CompressionType::NONE,
+        );
 
-        let resolved = searcher
-            .resolve_reference_line("natives/STM/test/sample.tex.999999999.STM.Ja")
-            .unwrap();
+        let dir = std::env::temp_dir().join(format!("ree-mmap-pak-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let pak_path = dir.join("re_chunk_000.pak");
+        std::fs::write(&pak_path, &pak_bytes).unwrap();
 
-        assert_eq!(resolved.len(), 1);
-        assert_eq!(
-            resolved[0].full_path,
-            "natives/STM/test/sample.tex.241106027.STM.Ja"
+        let searcher = PathSearcher::<MmapPakReader>::builder()
+            .with_config(config.clone())
+            .with_mapped_pak_paths(&[pak_path.clone(), dir.join("missing.pak")])
+            .with_skip_bad_paks(true)
+            .build()
+            .unwrap();
+        assert_eq!(searcher.rejected_paks().len(), 1);
+        assert_eq!(searcher.pak_file_count(), 2);
+        let [Some(RawPak::Mapped(mapping))] = &searcher.raw_paks[..] else {
+            panic!("the mapping must be kept for stored entries");
+        };
+        let source = searcher
+            .pak_collection()
+            .unwrap()
+            .entry_info_for_path("natives/STM/test/source.user.2")
+            .unwrap();
+        assert_eq!(source.compression, Compression::None);
+        assert_eq!(
+            mapping.bytes_at(source.offset, source.decompressed_size),
+            Some(&utf16_blob(&["test/a.tex"])[..])
+        );
+        let mapped = searcher.search_pak_files().unwrap();
+
+        let in_memory = PathSearcher::<Cursor<Vec<u8>>>::builder()
+            .with_config(config)
+            .with_pak_file(Cursor::new(pak_bytes))
+            .unwrap()
+            .build()
+            .unwrap()
+            .search_pak_files()
+            .unwrap();
+        let raw_paths = |result: &SearchResult| -> Vec<String> {
+            result.found_paths.iter().map(|(p, _)| p.clone()).collect()
+        };
+        assert_eq!(raw_paths(&mapped), ["test/a.tex"]);
+        assert_eq!(raw_paths(&mapped), raw_paths(&in_memory));
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn stored_entries_are_streamed_across_chunks() {
+        let utf16 = |s: &str| -> Vec<u8> {
+            let mut bytes: Vec<u8> = s.encode_utf16().flat_map(u16::to_le_bytes).collect();
+            bytes.extend([0, 0]);
+            bytes
+        };
+        let mut data = b"TESTDATA".to_vec();
+        data.extend(utf16("test/first.tex"));
+        // Start the second string at an odd offset so it straddles the first chunk boundary.
+        data.resize(scan_limits::STREAM_CHUNK_SIZE - 7, 0xFF);
+        data.extend(utf16("test/split.tex"));
+        data.resize(scan_limits::STREAM_CHUNK_SIZE + 100, 0xFF);
+
+        let searcher = PathSearcher::<Cursor<Vec<u8>>>::default();
+        let unk_paths = Mutex::new(FxHashSet::default());
+        let mut recorded = vec![];
+        let paths = searcher
+            .search_stored_entry(
+                data.as_slice(),
+                0,
+                data.len() as u64,
+                &mut None,
+                &unk_paths,
+                Some(&mut recorded),
+            )
+            .unwrap();
+
+        let raw_paths: Vec<&str> = paths.iter().map(|(p, _)| p.as_str()).collect();
+        assert_eq!(raw_paths, ["test/first.tex", "test/split.tex"]);
+        assert_eq!(recorded, ["test/first.tex", "test/split.tex"]);
+    }
+
+    #[test]
+    fn pak_scan_stops_at_path_target() {
+        let config = PathSearcherConfig::from_toml_str(
+            r#"
+languages = ["Ja"]
+prefixes = ["natives/STM/"]
+platform_suffixes = ["STM"]
+use_builtin_suffix_map = false
+
+[suffix_map]
+tex = [241106027]
+"#,
+        )
+        .unwrap();
+
+        let targets = ["test/a.tex", "test/b.tex", "test/c.tex"];
+        let files: Vec<(String, Vec<u8>)> = targets
+            .iter()
+            .enumerate()
+            .flat_map(|(i, target)| {
+                [
+                    (
+                        format!("natives/STM/{target}.241106027.STM"),
+                        b"test".to_vec(),
+                    ),
+                    (
+                        format!("natives/STM/test/source{i}.user.2"),
+                        utf16_blob(&[*target]),
+                    ),
+                ]
+            })
+            .collect();
+        let files: Vec<(&str, &[u8])> = files
+            .iter()
+            .map(|(path, data)| (path.as_str(), data.as_slice()))
+            .collect();
+        let pak_bytes = pak_bytes(&files);
+
+        let scan = |builder: PathSearcherBuilder<Cursor<Vec<u8>>>| {
+            builder
+                .with_config(config.clone())
+                .with_pak_file(Cursor::new(pak_bytes.clone()))
+                .unwrap()
+                .build()
+                .unwrap()
+                .search_pak_files()
+                .unwrap()
+        };
+
+        let full = scan(PathSearcher::builder());
+        assert!(!full.stopped_early);
+        assert_eq!(full.found_paths.len(), 3);
+
+        // One entry at a time, so the scan stops right after the first source.
+        let stopped = scan(
+            PathSearcher::builder()
+                .with_stop_after_paths(1)
+                .with_memory_budget(1),
+        );
+        assert!(stopped.stopped_early);
+        assert_eq!(stopped.found_paths.len(), 1);
+        assert!(stopped.entry_errors.is_empty());
+    }
+
+    #[test]
+    fn pak_scan_extracts_high_yield_entries_first() {
+        let config = PathSearcherConfig::from_toml_str(
+            r#"
+languages = ["Ja"]
+prefixes = ["natives/STM/"]
+platform_suffixes = ["STM"]
+use_builtin_suffix_map = false
+
+[suffix_map]
+tex = [241106027]
+user = [2]
+"#,
+        )
+        .unwrap();
+
+        let (low, high) = (
+            "natives/STM/test/low.tex.241106027.STM",
+            "natives/STM/test/high.user.2",
+        );
+        let pak_bytes = pak_bytes(&[
+            (low, &utf16_blob(&["test/a.tex"])),
+            (high, &utf16_blob(&["test/b.tex"])),
+            ("natives/STM/test/a.tex.241106027.STM", b"test"),
+            ("natives/STM/test/b.tex.241106027.STM", b"test"),
+        ]);
+
+        // The baseline tells the formats apart; the texture comes first in the PAK.
+        let result = PathSearcher::<Cursor<Vec<u8>>>::builder()
+            .with_config(config)
+            .with_pak_file(Cursor::new(pak_bytes))
+            .unwrap()
+            .with_baseline([low, high])
+            .with_stop_after_paths(1)
+            .with_memory_budget(1)
+            .build()
+            .unwrap()
+            .search_pak_files()
+            .unwrap();
+
+        assert!(result.stopped_early);
+        let raw_paths: Vec<&str> = result.found_paths.iter().map(|(p, _)| p.as_str()).collect();
+        assert_eq!(raw_paths, ["test/b.tex"]);
+    }
+
+    #[test]
+    fn truncated_entries_report_their_extraction_error() {
+        let (intact, truncated) = ("natives/STM/test/a.user.2", "natives/STM/test/b.user.2");
+        let mut pak_bytes = pak_bytes(&[
+            (intact, &utf16_blob(&["test/a.tex"])),
+            (truncated, &[0xFF; 4096]),
+        ]);
+        // The last entry's data ends the file, so cutting it short leaves the header intact.
+        pak_bytes.truncate(pak_bytes.len() - 1024);
+
+        let result = PathSearcher::<Cursor<Vec<u8>>>::builder()
+            .with_pak_file(Cursor::new(pak_bytes))
+            .unwrap()
+            .build()
+            .unwrap()
+            .search_pak_files()
+            .unwrap();
+        assert_eq!(result.entry_errors.len(), 1);
+        let error = &result.entry_errors[0];
+        assert_eq!(error.hash, hash::hash_path(truncated));
+        assert!(!error.message.is_empty());
+    }
+
+    #[test]
+    fn pak_scan_reuses_strings_of_unchanged_entries() {
+        let config = PathSearcherConfig::from_toml_str(
+            r#"
+languages = ["Ja"]
+prefixes = ["natives/STM/"]
+platform_suffixes = ["STM"]
+use_builtin_suffix_map = false
+
+[suffix_map]
+tex = [241106027]
+"#,
+        )
+        .unwrap();
+
+        let pak_bytes = pak_bytes(&[
+            ("natives/STM/test/raw.tex.241106027.STM", b"test"),
+            ("natives/STM/test/other.tex.241106027.STM", b"test"),
+            (
+                "natives/STM/test/source.user.2",
+                &utf16_blob(&["test/raw.tex"]),
+            ),
+        ]);
+
+        let dir = std::env::temp_dir().join(format!("ree-scan-reuse-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let state_path = dir.join("scan.state");
+        let scan = || {
+            PathSearcher::<Cursor<Vec<u8>>>::builder()
+                .with_config(config.clone())
+                .with_pak_file(Cursor::new(pak_bytes.clone()))
+                .unwrap()
+                .with_scan_state_file(&state_path)
+                .build()
+                .unwrap()
+                .search_pak_files()
+                .unwrap()
+        };
+        let raw_paths = |result: &SearchResult| -> Vec<String> {
+            result.found_paths.iter().map(|(p, _)| p.clone()).collect()
+        };
+
+        assert_eq!(raw_paths(&scan()), ["test/raw.tex"]);
+
+        // A second scan must take the strings from the state instead of the entry itself.
+        let state = std::fs::read_to_string(&state_path).unwrap();
+        assert!(state.contains("\ttest/raw.tex"));
+        std::fs::write(
+            &state_path,
+            state.replace("\ttest/raw.tex", "\ttest/other.tex"),
+        )
+        .unwrap();
+        assert_eq!(raw_paths(&scan()), ["test/other.tex"]);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn baseline_paths_are_not_new_but_their_variants_are() {
+        let config = PathSearcherConfig::from_toml_str(
+            r#"
+languages = ["Ja"]
+prefixes = ["natives/STM/"]
+platform_suffixes = ["STM"]
+use_builtin_suffix_map = false
+
+[suffix_map]
+tex = [241106027]
+"#,
+        )
+        .unwrap();
+        let searcher = build_searcher_with_baseline(
+            config,
+            &[
+                "natives/STM/test/known.tex.241106027",
+                "natives/STM/test/known.tex.241106027.STM",
+                "natives/STM/test/fresh.tex.241106027",
+            ],
+            &[
+                "natives/STM/test/known.tex.241106027",
+                "natives/STM/test/stale.tex.241106027",
+            ],
+        )
+        .unwrap();
+
+        // Baseline paths missing from the PAKs are known, but resolve nothing.
+        assert_eq!(searcher.cached_path_count(), 1);
+        assert!(searcher.is_known("natives/STM/test/stale.tex.241106027"));
+
+        let memory = utf16_blob(&["test/known.tex", "test/fresh.tex"]);
+        let unk_paths = Mutex::new(FxHashSet::default());
+        let found = searcher.search_memory(&memory, &unk_paths).unwrap();
+        assert_eq!(found.len(), 2);
+        // The known raw path is not looked up again, so its platform variant goes unseen.
+        assert_eq!(
+            searcher.new_paths(&found),
+            ["natives/STM/test/fresh.tex.241106027"]
+        );
+    }
+
+    #[test]
+    fn baseline_variants_are_found_on_request() {
+        let config = PathSearcherConfig::from_toml_str(
+            r#"
+languages = ["Ja"]
+prefixes = ["natives/STM/"]
+platform_suffixes = ["STM"]
+use_builtin_suffix_map = false
+
+[suffix_map]
+tex = [241106027]
+"#,
+        )
+        .unwrap();
+        let searcher = PathSearcher::<Cursor<Vec<u8>>>::builder()
+            .with_config(config)
+            .with_pak_file(pak(&[
+                ("natives/STM/test/known.tex.241106027", b"test"),
+                ("natives/STM/test/known.tex.241106027.STM", b"test"),
+            ]))
+            .unwrap()
+            .with_baseline(["natives/STM/test/known.tex.241106027"])
+            .with_resolve_baseline_variants(true)
+            .build()
+            .unwrap();
+        assert_eq!(searcher.cached_path_count(), 0);
+
+        let unk_paths = Mutex::new(FxHashSet::default());
+        let found = searcher
+            .search_memory(&utf16_blob(&["test/known.tex"]), &unk_paths)
+            .unwrap();
+        // The platform variant of a known raw path is a discovery too.
+        assert_eq!(
+            searcher.new_paths(&found),
+            ["natives/STM/test/known.tex.241106027.STM"]
+        );
+    }
+
+    #[test]
+    fn cache_saved_with_a_baseline_resolves_fully_without_one() {
+        let config = PathSearcherConfig::from_toml_str(
+            r#"
+languages = ["Ja"]
+prefixes = ["natives/STM/"]
+platform_suffixes = ["STM"]
+use_builtin_suffix_map = false
+
+[suffix_map]
+tex = [241106027]
+"#,
+        )
+        .unwrap();
+        let (plain, platform) = (
+            "natives/STM/test/known.tex.241106027",
+            "natives/STM/test/known.tex.241106027.STM",
+        );
+        let pak_bytes = pak_bytes(&[(plain, b"test"), (platform, b"test")]);
+
+        let dir = std::env::temp_dir().join(format!("ree-cache-baseline-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let cache_path = dir.join("paths.cache");
+        let resolve = |baseline: &[&str]| {
+            let searcher = PathSearcher::<Cursor<Vec<u8>>>::builder()
+                .with_config(config.clone())
+                .with_pak_file(Cursor::new(pak_bytes.clone()))
+                .unwrap()
+                .with_baseline(baseline.iter().copied())
+                .with_cache_file(&cache_path)
+                .build()
+                .unwrap();
+            let unk_paths = Mutex::new(FxHashSet::default());
+            let found = searcher
+                .search_memory(&utf16_blob(&["test/known.tex"]), &unk_paths)
+                .unwrap();
+            searcher.save_cache().unwrap();
+            let mut full_paths: Vec<String> = found
+                .into_iter()
+                .flat_map(|(_, infos)| infos.into_iter().map(|info| info.full_path))
+                .collect();
+            full_paths.sort();
+            full_paths
+        };
+
+        // The baseline names only one variant, which is all it resolves to; the cache must not
+        // keep that, so that without the baseline both are found.
+        assert_eq!(resolve(&[plain]), [plain]);
+        assert_eq!(resolve(&[]), [plain, platform]);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn search_memory_resolves_raw_and_full_paths() {
+        let config = PathSearcherConfig::from_toml_str(
+            r#"
+languages = ["Ja"]
+prefixes = ["natives/STM/"]
+platform_suffixes = ["STM"]
+use_builtin_suffix_map = false
+
+[suffix_map]
+tex = [241106027]
+"#,
+        )
+        .unwrap();
+        let searcher = build_searcher_with_paths(
+            config,
+            &[
+                "natives/STM/test/raw.tex.241106027.STM",
+                "natives/STM/test/full.tex.241106027",
+            ],
+        )
+        .unwrap();
+
+        let memory = utf16_blob(&[
+            "test/raw.tex",
+            "@natives/STM/test/full.tex.241106027",
+            "test/missing.tex",
+            "test/raw.tex",
+        ]);
+        let unk_paths = Mutex::new(FxHashSet::default());
+        let mut found = searcher.search_memory(&memory, &unk_paths).unwrap();
+        found.sort_by(|(p, _), (q, _)| p.cmp(q));
+        found.dedup_by(|(p, _), (q, _)| p == q);
+
+        let found: Vec<(&str, Vec<&str>)> = found
+            .iter()
+            .map(|(raw, infos)| {
+                (
+                    raw.as_str(),
+                    infos.iter().map(|i| i.full_path.as_str()).collect(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            found,
+            [
+                ("test/full.tex", vec!["natives/STM/test/full.tex.241106027"]),
+                (
+                    "test/raw.tex",
+                    vec!["natives/STM/test/raw.tex.241106027.STM"]
+                ),
+            ]
+        );
+        assert_eq!(
+            unk_paths.into_inner(),
+            FxHashSet::from_iter(["test/missing.tex".to_string()])
+        );
+    }
+
+    #[test]
+    fn resolve_reference_line_replaces_only_version_and_keeps_tail_tags() {
+        let config = PathSearcherConfig::from_toml_str(
+            r#"
+languages = ["Ja"]
+prefixes = ["natives/STM/"]
+platform_suffixes = ["STM"]
+use_builtin_suffix_map = false
+
+[suffix_map]
+tex = [241106027]
+"#,
+        )
+        .unwrap();
+
+        let searcher = build_searcher_with_paths(
+            config,
+            &[
+                "natives/STM/test/sample.tex.241106027.STM.Ja",
+                "natives/STM/test/sample.tex.999999999.STM.Ja",
+            ],
+        )
+        .unwrap();
+
+        let resolved = searcher
+            .resolve_reference_line("natives/STM/test/sample.tex.999999999.STM.Ja")
+            .unwrap();
+
+        assert_eq!(resolved.len(), 1);
+        assert_eq!(
+            resolved[0].full_path,
+            "natives/STM/test/sample.tex.241106027.STM.Ja"
+        );
+    }
+
+    #[test]
+    fn resolve_reference_line_collects_all_versions_when_enabled() {
+        let config_str = r#"
+languages = ["Ja"]
+prefixes = ["natives/STM/"]
+platform_suffixes = ["STM"]
+use_builtin_suffix_map = false
+
+[suffix_map]
+tex = [240701001, 241106027]
+"#;
+        let paths = [
+            "natives/STM/test/sample.tex.240701001",
+            "natives/STM/test/sample.tex.241106027",
+        ];
+
+        let newest_only = build_searcher_with_paths(
+            PathSearcherConfig::from_toml_str(config_str).unwrap(),
+            &paths,
+        )
+        .unwrap();
+        let all_versions = build_searcher_with_paths(
+            PathSearcherConfig::from_toml_str(config_str)
+                .unwrap()
+                .with_match_all_versions(true),
+            &paths,
+        )
+        .unwrap();
+
+        let full_paths = |searcher: &PathSearcher<_>, line| -> Vec<String> {
+            searcher
+                .resolve_reference_line(line)
+                .unwrap()
+                .into_iter()
+                .map(|info| info.full_path)
+                .collect()
+        };
+
+        let raw_line = "test/sample.tex";
+        assert_eq!(full_paths(&newest_only, raw_line), [paths[1]]);
+        assert_eq!(full_paths(&all_versions, raw_line), [paths[1], paths[0]]);
+
+        // Versioned lines keep collecting every version present, with or without the option.
+        let versioned_line = "natives/STM/test/sample.tex.1";
+        assert_eq!(full_paths(&newest_only, versioned_line), paths);
+        assert_eq!(full_paths(&all_versions, versioned_line), paths);
+    }
+
+    #[test]
+    fn resolve_reference_line_uses_platform_suffixes_of_each_prefix() {
+        let config = PathSearcherConfig::from_toml_str(
+            r#"
+languages = []
+prefixes = ["natives/STM/", "natives/NSW/"]
+platform_suffixes = ["X64", "STM"]
+use_builtin_suffix_map = false
+
+[prefix_platform_suffixes]
+"natives/NSW/" = ["NSW"]
+
+[suffix_map]
+tex = [241106027]
+"#,
+        )
+        .unwrap();
+
+        let searcher = build_searcher_with_paths(
+            config,
+            &[
+                "natives/STM/test/sample.tex.241106027.STM",
+                "natives/NSW/test/sample.tex.241106027.NSW",
+                "natives/NSW/test/sample.tex.241106027.STM",
+            ],
+        )
+        .unwrap();
+
+        let resolved = searcher.resolve_reference_line("test/sample.tex").unwrap();
+        let full_paths: Vec<&str> = resolved.iter().map(|i| i.full_path.as_str()).collect();
+
+        assert_eq!(
+            full_paths,
+            [
+                "natives/STM/test/sample.tex.241106027.STM",
+                "natives/NSW/test/sample.tex.241106027.NSW",
+            ]
+        );
+    }
+
+    #[test]
+    fn resolve_reference_line_follows_candidate_templates() {
+        let config = PathSearcherConfig::from_toml_str(
+            r#"
+languages = ["Ja"]
+prefixes = ["natives/STM/"]
+platform_suffixes = ["STM"]
+candidate_templates = ["{prefix}{streaming?}{raw}.{version}{.lang?}{.platform?}"]
+use_builtin_suffix_map = false
+
+[suffix_map]
+msg = [23]
+"#,
+        )
+        .unwrap();
+
+        let searcher = build_searcher_with_paths(
+            config,
+            &[
+                "natives/STM/test/sample.msg.23.Ja.STM",
+                "natives/STM/streaming/test/sample.msg.23.Ja.STM",
+                "natives/STM/test/sample.msg.23.STM.Ja",
+            ],
+        )
+        .unwrap();
+
+        let resolved = searcher.resolve_reference_line("test/sample.msg").unwrap();
+        let full_paths: Vec<&str> = resolved.iter().map(|i| i.full_path.as_str()).collect();
+
+        assert_eq!(
+            full_paths,
+            [
+                "natives/STM/test/sample.msg.23.Ja.STM",
+                "natives/STM/streaming/test/sample.msg.23.Ja.STM",
+            ]
         );
     }
 }
@@ -0,0 +1,140 @@
+//! On-disk copy of the resolution cache.
+//!
+//! Resolutions only hold for the PAK set and config they were made with, so the file starts with
+//! a header carrying both fingerprints and is ignored when either differs. Each following line is
+//! a raw path, then its resolved full paths, separated by tabs; a raw path alone is unresolvable.
+//!
+//! Resolutions taken from the baseline are never written, only those resolved against the PAKs,
+//! so a cache written with a baseline stays complete when loaded without one.
+
+use std::fs::{self, File};
+use std::io::{self, BufRead, BufReader, BufWriter, Write};
+use std::path::Path;
+
+use color_eyre::eyre::{self, Context};
+use dashmap::DashMap;
+use rustc_hash::FxBuildHasher;
+
+use super::suffix::I18nPakFileInfo;
+
+pub(super) type PathCache = DashMap<String, Option<Vec<I18nPakFileInfo>>, FxBuildHasher>;
+
+const MAGIC: &str = "ree-path-searcher-cache v1";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(super) struct CacheKey {
+    pub pak_fingerprint: u64,
+    pub config_fingerprint: u64,
+}
+
+impl CacheKey {
+    fn header(&self) -> String {
+        format!(
+            "{MAGIC} {:016x} {:016x}",
+            self.pak_fingerprint, self.config_fingerprint
+        )
+    }
+}
+
+/// Load the entries stored for `key` into `cache` and return how many were read. A missing file
+/// or one written for other PAKs or another config loads nothing.
+pub(super) fn load(path: &Path, key: CacheKey, cache: &PathCache) -> eyre::Result<usize> {
+    let file = match File::open(path) {
+        Ok(file) => file,
+        Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(0),
+        Err(err) => return Err(err).wrap_err_with(|| format!("Failed to open cache: {path:?}")),
+    };
+    let mut lines = BufReader::new(file).lines();
+    let header = lines.next().transpose()?;
+    if header.as_deref() != Some(key.header().as_str()) {
+        return Ok(0);
+    }
+
+    let mut count = 0;
+    for line in lines {
+        let line = line.wrap_err_with(|| format!("Failed to read cache: {path:?}"))?;
+        let mut fields = line.split('\t');
+        let Some(raw_path) = fields.next().filter(|raw| !raw.is_empty()) else {
+            continue;
+        };
+        let infos: Vec<I18nPakFileInfo> = fields
+            .map(|full_path| I18nPakFileInfo {
+                full_path: full_path.to_string(),
+            })
+            .collect();
+        cache.insert(raw_path.to_string(), (!infos.is_empty()).then_some(infos));
+        count += 1;
+    }
+    Ok(count)
+}
+
+/// Write `cache` for `key`, but for the raw paths `skip` returns true for, replacing the file
+/// only once it is complete.
+pub(super) fn save(
+    path: &Path,
+    key: CacheKey,
+    cache: &PathCache,
+    skip: impl Fn(&str) -> bool,
+) -> eyre::Result<()> {
+    let mut tmp_path = path.as_os_str().to_owned();
+    tmp_path.push(".tmp");
+
+    let file = File::create(&tmp_path)
+        .wrap_err_with(|| format!("Failed to create cache: {tmp_path:?}"))?;
+    let mut writer = BufWriter::new(file);
+    writeln!(writer, "{}", key.header())?;
+    for entry in cache.iter().filter(|entry| !skip(entry.key())) {
+        write!(writer, "{}", entry.key())?;
+        for info in entry.value().iter().flatten() {
+            write!(writer, "\t{}", info.full_path)?;
+        }
+        writeln!(writer)?;
+    }
+    writer.flush()?;
+    drop(writer);
+
+    fs::rename(&tmp_path, path).wrap_err_with(|| format!("Failed to write cache: {path:?}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cache_round_trips_for_the_same_key_only() {
+        let dir = std::env::temp_dir().join(format!("ree-path-cache-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("cache.txt");
+
+        let key = CacheKey {
+            pak_fingerprint: 1,
+            config_fingerprint: 2,
+        };
+        let cache = PathCache::default();
+        cache.insert(
+            "test/a.tex".to_string(),
+            Some(vec![I18nPakFileInfo {
+                full_path: "natives/STM/test/a.tex.1".to_string(),
+            }]),
+        );
+        cache.insert("test/missing.tex".to_string(), None);
+        save(&path, key, &cache, |_| false).unwrap();
+
+        let loaded = PathCache::default();
+        assert_eq!(load(&path, key, &loaded).unwrap(), 2);
+        assert!(loaded.get("test/missing.tex").unwrap().is_none());
+        let infos = loaded.get("test/a.tex").unwrap();
+        assert_eq!(
+            infos.value().as_ref().unwrap()[0].full_path,
+            "natives/STM/test/a.tex.1"
+        );
+
+        let other = CacheKey {
+            pak_fingerprint: 3,
+            ..key
+        };
+        assert_eq!(load(&path, other, &PathCache::default()).unwrap(), 0);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
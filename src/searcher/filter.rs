@@ -1,6 +1,12 @@
+use crate::magic;
+
+/// Length of the file header handed to filters in [`FileContext::data`].
+pub const HEADER_LEN: usize = 256;
+
 pub struct FileContext {
     pub file_size: u64,
     pub file_hash: Option<u64>,
+    /// The first [`HEADER_LEN`] bytes of the file, or all of it when shorter.
     pub data: Vec<u8>,
 }
 
@@ -23,29 +29,14 @@ impl Filter for DefaultFilter {
             return Ok(true);
         }
 
-        if check_skip_format(context.data[0..8].try_into().unwrap()) {
+        // Formats that hold no paths worth the scan.
+        if matches!(
+            magic::sniff_extension(&context.data),
+            Some("tex" | "sbnk" | "spck" | "msg")
+        ) {
             return Ok(true);
         }
 
         Ok(false)
     }
 }
-
-fn check_skip_format(magic: [u8; 8]) -> bool {
-    let magic_lower = u32::from_le_bytes(magic[0..4].try_into().unwrap());
-    let magic_upper = u32::from_le_bytes(magic[4..8].try_into().unwrap());
-
-    match magic_lower {
-        0x584554 => return true,   // tex
-        0x44484B42 => return true, // bnk
-        0x4B504B41 => return true, // pck
-        _ => {}
-    };
-    #[allow(clippy::single_match)]
-    match magic_upper {
-        0x47534D47 => return true, // msg
-        _ => {}
-    };
-
-    false
-}
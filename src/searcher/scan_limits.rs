@@ -0,0 +1,132 @@
+//! Limits on how much decompressed data a PAK scan holds at once, and on how long it runs.
+//!
+//! Entries are extracted in parallel, so a scan can hold one decompressed entry per thread. With
+//! a memory budget, entries too large to share it with every other thread are moved to a
+//! sequential pass that extracts them one at a time. Entries above the max entry size are not
+//! extracted at all; stored (uncompressed) ones can instead be read straight from the PAK in
+//! chunks, which never holds more than one chunk of them.
+//!
+//! A scan can also stop early, once it found enough paths or ran out of time.
+
+use std::time::Duration;
+
+use super::scan_state::EntryStamp;
+
+/// Size of the chunks huge stored entries are read in.
+pub(super) const STREAM_CHUNK_SIZE: usize = 4 << 20;
+
+/// How far back from the end of a chunk a safe place to cut it is looked for. Strings longer than
+/// this may be split in two when they cross a chunk boundary.
+const MAX_CARRY: usize = 64 << 10;
+
+#[derive(Debug, Clone, Copy, Default)]
+pub(super) struct ScanLimits {
+    pub memory_budget: Option<u64>,
+    pub max_entry_size: Option<u64>,
+    pub stream_large_entries: bool,
+    pub stop_after_paths: Option<usize>,
+    pub time_budget: Option<Duration>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub(super) enum ScanPass {
+    /// Stored entries of a memory-mapped PAK, scanned in place. The scan picks this for the
+    /// entries [`ScanLimits::pass_for`] lets be extracted, as it knows nothing of the PAK.
+    Mapped,
+    Parallel,
+    Sequential,
+    Streamed,
+    TooLarge,
+}
+
+impl ScanLimits {
+    pub fn pass_for(&self, stamp: EntryStamp, threads: usize) -> ScanPass {
+        let size = stamp.decompressed_size;
+        if self.max_entry_size.is_some_and(|max| size > max) {
+            // Only stored entries can be read without decompressing them.
+            return if self.stream_large_entries && stamp.stored {
+                ScanPass::Streamed
+            } else {
+                ScanPass::TooLarge
+            };
+        }
+        match self.memory_budget {
+            Some(budget) if size > budget / threads.max(1) as u64 => ScanPass::Sequential,
+            _ => ScanPass::Parallel,
+        }
+    }
+}
+
+/// Length of the prefix of `chunk` that can be scanned on its own, leaving the rest to be
+/// carried into the next chunk.
+///
+/// A UTF-16 path alternates ASCII bytes with zero bytes, so two neighbouring bytes that are both
+/// zero or both non-zero never belong to the same path, at any alignment. Cutting between them
+/// splits no string.
+pub(super) fn safe_cut(chunk: &[u8]) -> usize {
+    let floor = chunk.len().saturating_sub(MAX_CARRY).max(1);
+    (floor..chunk.len())
+        .rev()
+        .find(|&i| (chunk[i - 1] == 0) == (chunk[i] == 0))
+        .unwrap_or(chunk.len())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn entries_are_assigned_to_passes_by_size() {
+        let stamp = |compressed_size, decompressed_size, stored| EntryStamp {
+            pak: 0,
+            offset: 0,
+            compressed_size,
+            decompressed_size,
+            stored,
+        };
+        let limits = ScanLimits {
+            memory_budget: Some(400),
+            max_entry_size: Some(1000),
+            stream_large_entries: true,
+            ..ScanLimits::default()
+        };
+        assert_eq!(
+            limits.pass_for(stamp(50, 100, false), 4),
+            ScanPass::Parallel
+        );
+        assert_eq!(
+            limits.pass_for(stamp(50, 500, false), 4),
+            ScanPass::Sequential
+        );
+        assert_eq!(
+            limits.pass_for(stamp(2000, 2000, true), 4),
+            ScanPass::Streamed
+        );
+        assert_eq!(
+            limits.pass_for(stamp(500, 2000, false), 4),
+            ScanPass::TooLarge
+        );
+        // Equal sizes do not make an entry stored; a compressed one cannot be streamed raw.
+        assert_eq!(
+            limits.pass_for(stamp(2000, 2000, false), 4),
+            ScanPass::TooLarge
+        );
+        assert_eq!(
+            ScanLimits::default().pass_for(stamp(1 << 40, 1 << 40, true), 4),
+            ScanPass::Parallel
+        );
+    }
+
+    #[test]
+    fn cuts_between_strings() {
+        let chunk: Vec<u8> = "a/b".encode_utf16().flat_map(u16::to_le_bytes).collect();
+        // Inside the string every byte pair mixes zero and non-zero.
+        assert_eq!(safe_cut(&chunk), chunk.len());
+
+        let mut chunk = chunk;
+        chunk.extend([0, 0]);
+        chunk.extend("c/".encode_utf16().flat_map(u16::to_le_bytes));
+        // Right after the terminator, before the unfinished second string.
+        assert_eq!(safe_cut(&chunk), 7);
+    }
+}
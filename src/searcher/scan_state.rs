@@ -0,0 +1,230 @@
+//! Candidate strings found in each scanned PAK entry, kept across runs.
+//!
+//! Scanning means decompressing every entry, while resolving the strings it yields is cheap and
+//! depends on the current PAK set and config. The state therefore stores the unresolved strings
+//! per entry hash, together with a stamp of the entry's PAK, location and sizes; an entry whose
+//! stamp is unchanged is not extracted again and its strings are simply resolved anew. The format
+//! sniffed from each entry is kept as well, to schedule the entry by its yield when it changes.
+
+use std::fs::{self, File};
+use std::io::{self, BufRead, BufReader, BufWriter, Write};
+use std::path::Path;
+
+use color_eyre::eyre::{self, Context};
+use rustc_hash::FxHashMap;
+
+const MAGIC: &str = "ree-path-searcher-scan-state v4";
+
+/// Where an entry is stored and how large it is. A patched entry gets a new stamp, and so does
+/// one a newly added PAK now provides, even at the same offset and sizes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(super) struct EntryStamp {
+    /// Hash of the file name of the PAK the entry is read from.
+    pub pak: u64,
+    pub offset: u64,
+    pub compressed_size: u64,
+    pub decompressed_size: u64,
+    /// Whether the entry is stored as is, neither compressed nor encrypted, so its bytes in the
+    /// PAK are its contents.
+    pub stored: bool,
+}
+
+#[derive(Debug, Clone)]
+struct ScannedEntry {
+    stamp: EntryStamp,
+    /// Extension sniffed from the entry's magic, empty when unknown.
+    format: String,
+    paths: Vec<String>,
+}
+
+#[derive(Debug, Default)]
+pub(super) struct ScanState {
+    entries: FxHashMap<u64, ScannedEntry>,
+}
+
+impl ScanState {
+    /// Load a state file. A missing file or one in another format gives an empty state.
+    pub fn load(path: &Path) -> eyre::Result<Self> {
+        let file = match File::open(path) {
+            Ok(file) => file,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(Self::default()),
+            Err(err) => {
+                return Err(err).wrap_err_with(|| format!("Failed to open scan state: {path:?}"));
+            }
+        };
+        let mut lines = BufReader::new(file).lines();
+        let header = lines.next().transpose()?;
+        if header.as_deref() != Some(MAGIC) {
+            return Ok(Self::default());
+        }
+
+        let mut state = Self::default();
+        for line in lines {
+            let line = line.wrap_err_with(|| format!("Failed to read scan state: {path:?}"))?;
+            let mut fields = line.split('\t');
+            let mut number = |radix| {
+                fields
+                    .next()
+                    .and_then(|field| u64::from_str_radix(field, radix).ok())
+            };
+            let (
+                Some(hash),
+                Some(pak),
+                Some(offset),
+                Some(compressed_size),
+                Some(decompressed_size),
+                Some(stored),
+            ) = (
+                number(16),
+                number(16),
+                number(10),
+                number(10),
+                number(10),
+                number(10),
+            )
+            else {
+                continue;
+            };
+            let Some(format) = fields.next() else {
+                continue;
+            };
+            state.entries.insert(
+                hash,
+                ScannedEntry {
+                    stamp: EntryStamp {
+                        pak,
+                        offset,
+                        compressed_size,
+                        decompressed_size,
+                        stored: stored != 0,
+                    },
+                    format: format.to_string(),
+                    paths: fields.map(str::to_string).collect(),
+                },
+            );
+        }
+        Ok(state)
+    }
+
+    /// Write the state, replacing the file only once it is complete.
+    pub fn save(&self, path: &Path) -> eyre::Result<()> {
+        let mut tmp_path = path.as_os_str().to_owned();
+        tmp_path.push(".tmp");
+
+        let file = File::create(&tmp_path)
+            .wrap_err_with(|| format!("Failed to create scan state: {tmp_path:?}"))?;
+        let mut writer = BufWriter::new(file);
+        writeln!(writer, "{MAGIC}")?;
+        for (hash, entry) in &self.entries {
+            let stamp = entry.stamp;
+            write!(
+                writer,
+                "{hash:016x}\t{:016x}\t{}\t{}\t{}\t{}\t{}",
+                stamp.pak,
+                stamp.offset,
+                stamp.compressed_size,
+                stamp.decompressed_size,
+                u8::from(stamp.stored),
+                entry.format
+            )?;
+            for path in &entry.paths {
+                write!(writer, "\t{path}")?;
+            }
+            writeln!(writer)?;
+        }
+        writer.flush()?;
+        drop(writer);
+
+        fs::rename(&tmp_path, path)
+            .wrap_err_with(|| format!("Failed to write scan state: {path:?}"))
+    }
+
+    /// Strings recorded for `hash`, if the entry still has the same stamp.
+    pub fn get(&self, hash: u64, stamp: EntryStamp) -> Option<&[String]> {
+        self.entries
+            .get(&hash)
+            .filter(|entry| entry.stamp == stamp)
+            .map(|entry| entry.paths.as_slice())
+    }
+
+    /// Format last sniffed from `hash`, whether or not the entry changed since.
+    pub fn format(&self, hash: u64) -> Option<&str> {
+        self.entries
+            .get(&hash)
+            .map(|entry| entry.format.as_str())
+            .filter(|format| !format.is_empty())
+    }
+
+    pub fn insert(
+        &mut self,
+        hash: u64,
+        stamp: EntryStamp,
+        format: Option<&str>,
+        paths: Vec<String>,
+    ) {
+        let format = format.unwrap_or_default().to_string();
+        self.entries.insert(
+            hash,
+            ScannedEntry {
+                stamp,
+                format,
+                paths,
+            },
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn scan_state_round_trips() {
+        let dir = std::env::temp_dir().join(format!("ree-scan-state-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("scan.state");
+
+        let stamp = EntryStamp {
+            pak: 0x5A5A,
+            offset: 64,
+            compressed_size: 10,
+            decompressed_size: 20,
+            stored: false,
+        };
+        let mut state = ScanState::default();
+        state.insert(
+            0xABCD,
+            stamp,
+            Some("user"),
+            vec!["test/a.tex".into(), "test/b.mdf2".into()],
+        );
+        state.insert(0x1234, stamp, None, vec![]);
+        state.save(&path).unwrap();
+
+        let loaded = ScanState::load(&path).unwrap();
+        assert_eq!(
+            loaded.get(0xABCD, stamp).unwrap(),
+            ["test/a.tex", "test/b.mdf2"]
+        );
+        assert!(loaded.get(0x1234, stamp).unwrap().is_empty());
+        assert_eq!(loaded.format(0xABCD), Some("user"));
+        assert_eq!(loaded.format(0x1234), None);
+        let moved = EntryStamp {
+            offset: 128,
+            ..stamp
+        };
+        assert!(loaded.get(0xABCD, moved).is_none());
+        let shadowed = EntryStamp {
+            pak: 0x6B6B,
+            ..stamp
+        };
+        assert!(loaded.get(0xABCD, shadowed).is_none());
+        let recompressed = EntryStamp {
+            stored: true,
+            ..stamp
+        };
+        assert!(loaded.get(0xABCD, recompressed).is_none());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -1,7 +1,10 @@
+use std::fmt::Write as _;
+
 use color_eyre::eyre::{self, ContextCompat};
 use ree_pak_core::PakReader;
 
-use crate::config::PathSearcherConfig;
+use crate::config::{PathSearcherConfig, SlotKind, TemplateSegment};
+use crate::hash::PathHasher;
 use crate::pak;
 use crate::path_components::PathComponents;
 
@@ -15,69 +18,177 @@ pub fn find_path_i18n<R: PakReader>(
     config: &PathSearcherConfig,
     parts: &PathComponents<'_>,
 ) -> eyre::Result<Vec<I18nPakFileInfo>> {
-    let raw_path = parts.raw_path();
-    let ext = parts.extension().context("Path missing extension")?;
+    find_raw_path_i18n(pak, config, parts.raw_path())
+}
+
+/// Same as [`find_path_i18n`], for a raw path that was already split off its full path.
+pub fn find_raw_path_i18n<R: PakReader>(
+    pak: &pak::PakCollection<R>,
+    config: &PathSearcherConfig,
+    raw_path: &str,
+) -> eyre::Result<Vec<I18nPakFileInfo>> {
+    let ext = raw_path
+        .rfind('.')
+        .map(|dot| &raw_path[dot + 1..])
+        .context("Path missing extension")?;
     let versions = config
         .suffix_versions(ext)
         .context(format!("Unknown extension: {ext}"))?;
+
+    // Candidates are hashed incrementally; the scratch buffer only mirrors the current candidate
+    // so that hits can be turned into strings.
+    let mut buf = String::with_capacity(raw_path.len() + 64);
+    let mut version_buf = String::with_capacity(10);
+    let mut result = vec![];
     for &version in versions.iter().rev() {
-        let mut result = vec![];
-
-        let mut candidates =
-            Vec::with_capacity(config.prefixes().len() * (2 + config.platform_suffixes().len()));
-        for prefix in config.prefixes() {
-            let base = format!("{prefix}{raw_path}.{version}");
-            candidates.push(base.clone());
-            for suffix in config.platform_suffixes() {
-                candidates.push(format!("{base}.{suffix}"));
-            }
+        version_buf.clear();
+        let _ = write!(&mut version_buf, "{version}");
+        // (full path, insert position of an optional `streaming/`)
+        let mut hits: Vec<(String, Option<usize>)> = vec![];
+
+        for template in config.candidate_templates() {
+            buf.clear();
+            let mut expander = CandidateExpander {
+                config,
+                raw_path,
+                version: version_buf.as_str(),
+                prefix: None,
+                buf: &mut buf,
+                visit: |candidate: &str, hash: u64, streaming_at: Option<usize>| {
+                    if pak.contains_hash(hash) && !hits.iter().any(|(path, _)| path == candidate) {
+                        hits.push((candidate.to_string(), streaming_at));
+                    }
+                },
+            };
+            expander.expand(template.segments(), PathHasher::new(), None);
         }
 
-        for full_path in &candidates {
-            // Check base path first (no language suffix)
-            if pak.contains_path(full_path) {
-                result.push(I18nPakFileInfo {
-                    full_path: full_path.clone(),
-                });
-            }
+        if hits.is_empty() {
+            continue;
+        }
 
-            // Then check with language suffixes
-            for language in config.languages() {
-                let with_language = format!("{full_path}.{language}");
-                if pak.contains_path(&with_language) {
-                    result.push(I18nPakFileInfo {
-                        full_path: with_language,
+        // try to find streaming file
+        let mut streaming_result = vec![];
+        for (full_path, streaming_at) in &hits {
+            if let Some(pos) = *streaming_at {
+                let mut streaming_path = full_path.clone();
+                streaming_path.insert_str(pos, "streaming/");
+                if pak.contains_path(&streaming_path) {
+                    streaming_result.push(I18nPakFileInfo {
+                        full_path: streaming_path,
                     });
                 }
             }
         }
 
-        if !result.is_empty() {
-            // try to find streaming file
-            let mut streaming_result = vec![];
-            for info in &result {
-                let mut pos = 0;
+        result.extend(
+            hits.into_iter()
+                .map(|(full_path, _)| I18nPakFileInfo { full_path }),
+        );
+        result.extend(streaming_result);
+
+        if !config.match_all_versions() {
+            break;
+        }
+    }
+
+    Ok(result)
+}
+
+/// Walks every combination of slot values of a template, depth-first.
+///
+/// The hash state is copied at each branch so shared parts are hashed once.
+struct CandidateExpander<'a, 'b, F> {
+    config: &'a PathSearcherConfig,
+    raw_path: &'a str,
+    version: &'a str,
+    /// Prefix chosen by an enclosing `{prefix}` slot, narrowing the platform suffixes.
+    prefix: Option<&'a str>,
+    buf: &'b mut String,
+    visit: F,
+}
+
+impl<F> CandidateExpander<'_, '_, F>
+where
+    F: FnMut(&str, u64, Option<usize>),
+{
+    fn expand(
+        &mut self,
+        segments: &[TemplateSegment],
+        hasher: PathHasher,
+        streaming_at: Option<usize>,
+    ) {
+        let Some((first, rest)) = segments.split_first() else {
+            (self.visit)(self.buf.as_str(), hasher.finish(), streaming_at);
+            return;
+        };
+
+        let slot = match first {
+            TemplateSegment::Literal(literal) => {
+                self.expand_with(literal, false, rest, hasher, streaming_at);
+                return;
+            }
+            TemplateSegment::Slot(slot) => slot,
+        };
+
+        if slot.kind == SlotKind::Streaming {
+            // Streaming variants are only probed for hits, see `find_path_i18n`.
+            let pos = self.buf.len();
+            self.expand(rest, hasher, Some(pos));
+            return;
+        }
+        if slot.optional {
+            self.expand(rest, hasher, streaming_at);
+        }
+
+        let config = self.config;
+        match slot.kind {
+            SlotKind::Prefix => {
+                let outer = self.prefix;
                 for prefix in config.prefixes() {
-                    if let Some(prefix_pos) = info.full_path.find(prefix.as_str()) {
-                        pos = prefix_pos + prefix.len();
-                        break;
-                    }
+                    self.prefix = Some(prefix);
+                    self.expand_with(prefix, slot.leading_dot, rest, hasher, streaming_at);
                 }
-                if pos > 0 {
-                    let mut streaming_path = info.full_path.clone();
-                    streaming_path.insert_str(pos, "streaming/");
-                    if pak.contains_path(&streaming_path) {
-                        streaming_result.push(I18nPakFileInfo {
-                            full_path: streaming_path,
-                        });
-                    }
+                self.prefix = outer;
+            }
+            SlotKind::Raw => {
+                let raw_path = self.raw_path;
+                self.expand_with(raw_path, slot.leading_dot, rest, hasher, streaming_at);
+            }
+            SlotKind::Version => {
+                let version = self.version;
+                self.expand_with(version, slot.leading_dot, rest, hasher, streaming_at);
+            }
+            SlotKind::Platform => {
+                for suffix in config.platform_suffixes_for_prefix(self.prefix) {
+                    self.expand_with(suffix, slot.leading_dot, rest, hasher, streaming_at);
                 }
             }
-            result.extend(streaming_result);
-
-            return Ok(result);
+            SlotKind::Language => {
+                for language in config.languages() {
+                    self.expand_with(language, slot.leading_dot, rest, hasher, streaming_at);
+                }
+            }
+            SlotKind::Streaming => unreachable!(),
         }
     }
 
-    Ok(vec![])
+    fn expand_with(
+        &mut self,
+        value: &str,
+        leading_dot: bool,
+        rest: &[TemplateSegment],
+        mut hasher: PathHasher,
+        streaming_at: Option<usize>,
+    ) {
+        let len = self.buf.len();
+        if leading_dot {
+            self.buf.push('.');
+            hasher.write_char('.');
+        }
+        self.buf.push_str(value);
+        hasher.write_str(value);
+        self.expand(rest, hasher, streaming_at);
+        self.buf.truncate(len);
+    }
 }
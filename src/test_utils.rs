@@ -0,0 +1,35 @@
+//! Fixtures shared by the unit tests.
+
+use std::io::{Cursor, Write};
+
+use ree_pak_core::pak::CompressionType;
+use ree_pak_core::write::{FileOptions, PakWriter};
+
+/// Bytes of a PAK holding `files`, as `(path, data)` pairs in order.
+pub(crate) fn pak_bytes(files: &[(&str, &[u8])]) -> Vec<u8> {
+    write_pak(files, FileOptions::default())
+}
+
+/// [`pak_bytes`] with every entry written with `compression`.
+pub(crate) fn pak_bytes_with(files: &[(&str, &[u8])], compression: CompressionType) -> Vec<u8> {
+    write_pak(
+        files,
+        FileOptions::default().with_compression_type(compression),
+    )
+}
+
+fn write_pak(files: &[(&str, &[u8])], options: FileOptions) -> Vec<u8> {
+    let mut bytes = Vec::new();
+    let mut writer = PakWriter::new(Cursor::new(&mut bytes), files.len() as u64);
+    for (path, data) in files {
+        writer.start_file(*path, options.clone()).unwrap();
+        writer.write_all(data).unwrap();
+    }
+    writer.finish().unwrap();
+    bytes
+}
+
+/// [`pak_bytes`] as an in-memory reader.
+pub(crate) fn pak(files: &[(&str, &[u8])]) -> Cursor<Vec<u8>> {
+    Cursor::new(pak_bytes(files))
+}
@@ -0,0 +1,228 @@
+//! PAK entries no known path names, classified by the format sniffed from their contents.
+//!
+//! The extension is the first part of a guessed path that has to be right, and it also decides
+//! which versions are worth probing. Guesses are therefore only tried with the extensions unnamed
+//! entries were sniffed as, and only count when they hit an entry of that extension.
+
+use std::collections::BTreeMap;
+use std::sync::Arc;
+
+use color_eyre::eyre;
+use parking_lot::Mutex;
+use ree_pak_core::PakReader;
+use rustc_hash::{FxHashMap, FxHashSet};
+
+use crate::hash;
+use crate::magic;
+use crate::pak::{self, PakCollection, RawPak};
+
+/// Size above which entries are not extracted to sniff them, unless told otherwise. Stored
+/// entries with a raw source only ever have their header read.
+pub const DEFAULT_MAX_SNIFF_SIZE: u64 = 64 << 20;
+
+#[derive(Debug, Clone, Default)]
+pub struct UnnamedEntries {
+    /// Sniffed extension of each unnamed entry, `None` while unknown.
+    formats: FxHashMap<u64, Option<&'static str>>,
+}
+
+impl UnnamedEntries {
+    /// The entries of `collection` none of `full_paths` hash to, all of unknown format yet.
+    pub fn new<R, I>(collection: &PakCollection<R>, full_paths: I) -> Self
+    where
+        R: PakReader,
+        I: IntoIterator,
+        I::Item: AsRef<str>,
+    {
+        let mut formats: FxHashMap<u64, Option<&'static str>> = collection
+            .pak_files()
+            .iter()
+            .flat_map(|pak| pak.metadata().entries().iter().map(|entry| entry.hash()))
+            .map(|hash| (hash, None))
+            .collect();
+        for path in full_paths {
+            formats.remove(&hash::hash_path(path.as_ref()));
+        }
+        Self { formats }
+    }
+
+    pub fn len(&self) -> usize {
+        self.formats.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.formats.is_empty()
+    }
+
+    pub fn contains(&self, hash: u64) -> bool {
+        self.formats.contains_key(&hash)
+    }
+
+    /// Sniffed extension of the unnamed entry `hash`.
+    pub fn format(&self, hash: u64) -> Option<&'static str> {
+        self.formats.get(&hash).copied().flatten()
+    }
+
+    /// Record the format of `hash`, e.g. as sniffed by an earlier scan. Named entries are ignored.
+    pub fn set_format(&mut self, hash: u64, extension: &'static str) {
+        if let Some(format) = self.formats.get_mut(&hash) {
+            *format = Some(extension);
+        }
+    }
+
+    /// Unnamed entries whose format is not known yet.
+    pub fn unclassified(&self) -> impl Iterator<Item = u64> + '_ {
+        self.formats
+            .iter()
+            .filter(|(_, format)| format.is_none())
+            .map(|(&hash, _)| hash)
+    }
+
+    /// Sniff the entries of unknown format from their contents, each from the PAK the game loads
+    /// it from. Entries stored without compression in a PAK with a raw source only have their
+    /// header read; the others are extracted unless above `max_entry_size` bytes once
+    /// decompressed, [`DEFAULT_MAX_SNIFF_SIZE`] by default. Entries of no known format stay
+    /// unknown.
+    pub fn classify<R: PakReader>(
+        &mut self,
+        collection: &PakCollection<R>,
+        raw_paks: &[Option<RawPak>],
+        max_entry_size: Option<u64>,
+    ) -> eyre::Result<()> {
+        let max_entry_size = max_entry_size.unwrap_or(DEFAULT_MAX_SNIFF_SIZE);
+        let sniffed = Mutex::new(vec![]);
+        for (pak_index, pak) in collection.pak_files().iter().enumerate() {
+            let raw = raw_paks.get(pak_index).and_then(Option::as_ref);
+            let mut headers: Vec<(u64, (u64, u64))> = vec![];
+            let mut pending: FxHashSet<u64> = FxHashSet::default();
+            for entry in pak.metadata().entries().iter() {
+                let hash = entry.hash();
+                if !matches!(self.formats.get(&hash), Some(None))
+                    || !collection.should_scan_hash_in_pak(hash, pak_index)
+                {
+                    continue;
+                }
+                let size = entry.decompressed_size();
+                if raw.is_some() && pak::is_stored(entry) {
+                    let len = size.min(magic::HEADER_LEN as u64);
+                    headers.push((hash, (entry.offset(), len)));
+                } else if size <= max_entry_size {
+                    pending.insert(hash);
+                }
+            }
+
+            if let Some(raw) = raw
+                && !headers.is_empty()
+            {
+                headers.sort_unstable_by_key(|&(_, range)| range);
+                let ranges: Vec<(u64, u64)> = headers.iter().map(|&(_, range)| range).collect();
+                for ((hash, _), header) in headers.iter().zip(raw.headers(&ranges)?) {
+                    if let Some(extension) = magic::sniff_extension(&header) {
+                        sniffed.lock().push((*hash, extension));
+                    }
+                }
+            }
+            if pending.is_empty() {
+                continue;
+            }
+
+            let pending = Arc::new(pending);
+            pak.extractor_callback()
+                .parallel(true)
+                .continue_on_error(true)
+                .filter(move |entry, _path| pending.contains(&entry.hash()))
+                .run_with_bytes(|entry, _rel_path, bytes| {
+                    if let Some(extension) = magic::sniff_extension(&bytes) {
+                        sniffed.lock().push((entry.hash(), extension));
+                    }
+                    Ok(())
+                })?;
+        }
+
+        for (hash, extension) in sniffed.into_inner() {
+            self.set_format(hash, extension);
+        }
+        Ok(())
+    }
+
+    /// Hashes of the unnamed entries of each sniffed extension, sorted.
+    pub fn by_extension(&self) -> BTreeMap<&'static str, Vec<u64>> {
+        let mut groups: BTreeMap<&'static str, Vec<u64>> = BTreeMap::new();
+        for (&hash, format) in &self.formats {
+            if let Some(extension) = format {
+                groups.entry(extension).or_default().push(hash);
+            }
+        }
+        for hashes in groups.values_mut() {
+            hashes.sort_unstable();
+        }
+        groups
+    }
+
+    /// Whether a path ending in `extension` could name the entry `hash`: it must be unnamed, and
+    /// either of that extension or of unknown format.
+    pub fn accepts(&self, hash: u64, extension: &str) -> bool {
+        match self.formats.get(&hash) {
+            Some(Some(format)) => *format == extension,
+            Some(None) => true,
+            None => false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use ree_pak_core::pak::CompressionType;
+
+    use super::*;
+    use crate::test_utils::{pak, pak_bytes_with};
+
+    #[test]
+    fn classifies_unnamed_entries_by_magic() {
+        let (named, scene, unknown) = (
+            "natives/STM/test/a.user.2",
+            "natives/STM/test/b.scn.21",
+            "natives/STM/test/c.bin",
+        );
+        let files: [(&str, &[u8]); 3] = [
+            (named, b"USR\0\0\0\0\0"),
+            (scene, b"SCN\0\0\0\0\0"),
+            (unknown, b"\x01\x02\x03\x04\0\0\0\0"),
+        ];
+        let collection = PakCollection::from_readers(vec![pak(&files)]).unwrap();
+
+        let mut unnamed = UnnamedEntries::new(&collection, [named]);
+        assert_eq!(unnamed.len(), 2);
+        unnamed.classify(&collection, &[], None).unwrap();
+
+        let (scene, unknown) = (hash::hash_path(scene), hash::hash_path(unknown));
+        assert_eq!(unnamed.format(scene), Some("scn"));
+        assert_eq!(unnamed.unclassified().collect::<Vec<_>>(), [unknown]);
+        assert_eq!(unnamed.by_extension().get("scn"), Some(&vec![scene]));
+
+        assert!(unnamed.accepts(scene, "scn"));
+        assert!(!unnamed.accepts(scene, "user"));
+        assert!(unnamed.accepts(unknown, "user"));
+        assert!(!unnamed.accepts(hash::hash_path(named), "user"));
+
+        // With the PAK file at hand, stored entries are sniffed from their header alone, while
+        // compressed ones are still extracted.
+        let dir = std::env::temp_dir().join(format!("ree-unnamed-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("re_chunk_000.pak");
+        for compression in [CompressionType::NONE, CompressionType::ZSTD] {
+            let pak_bytes = pak_bytes_with(&files, compression);
+            std::fs::write(&path, &pak_bytes).unwrap();
+            let collection = PakCollection::from_readers(vec![Cursor::new(pak_bytes)]).unwrap();
+            let mut from_file = UnnamedEntries::new(&collection, [named]);
+            from_file
+                .classify(&collection, &[Some(RawPak::File(path.clone()))], None)
+                .unwrap();
+            assert_eq!(from_file.by_extension(), unnamed.by_extension());
+            assert_eq!(from_file.unclassified().collect::<Vec<_>>(), [unknown]);
+        }
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}